@@ -1,13 +1,64 @@
-use std::{fs, ops::Range};
+use std::{env, fs, ops::Range};
 
 fn main() {
     let contents = fs::read_to_string("input").unwrap();
+    if env::args().nth(1).as_deref() == Some("report") {
+        println!("Day02 part 1 report:");
+        print_report(&report(&contents, part1::get_repetition));
+        println!("Day02 part 2 report:");
+        print_report(&report(&contents, part2::get_repetition));
+        return;
+    }
     let result = part1::day02(&contents);
     println!("Day02 part 1 result: {result}");
     let result = part2::day02(&contents);
     println!("Day02 part 2 result: {result}");
 }
 
+#[derive(Debug, PartialEq)]
+struct InvalidId {
+    id: usize,
+    block: usize,
+    repeats: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct RangeReport {
+    range: Range<usize>,
+    invalid_ids: Vec<InvalidId>,
+}
+
+// Lists the invalid IDs of every range as written in the input, so IDs in overlapping ranges
+// appear once per range
+fn report(input: &str, get_repetition: fn(usize) -> Option<(usize, u32)>) -> Vec<RangeReport> {
+    read_input_ranges(input)
+        .into_iter()
+        .map(|range| RangeReport {
+            invalid_ids: range
+                .clone()
+                .filter_map(|n| {
+                    get_repetition(n).map(|(block, repeats)| InvalidId {
+                        id: n,
+                        block,
+                        repeats,
+                    })
+                })
+                .collect(),
+            range,
+        })
+        .collect()
+}
+
+fn print_report(report: &[RangeReport]) {
+    for range_report in report {
+        let range = &range_report.range;
+        println!("{}-{}:", range.start, range.end - 1);
+        for invalid in &range_report.invalid_ids {
+            println!("  {} = {} x {}", invalid.id, invalid.block, invalid.repeats);
+        }
+    }
+}
+
 mod part1 {
     use super::*;
 
@@ -23,11 +74,16 @@ mod part1 {
     }
 
     fn is_invalid(n: usize) -> bool {
+        get_repetition(n).is_some()
+    }
+
+    pub(super) fn get_repetition(n: usize) -> Option<(usize, u32)> {
         let digits = n.ilog10() + 1;
-        if digits % 2 != 0 {
-            return false;
+        if !digits.is_multiple_of(2) {
+            return None;
         }
-        n / 10_usize.pow(digits / 2) == n % 10_usize.pow(digits / 2)
+        let block = n % 10_usize.pow(digits / 2);
+        (n / 10_usize.pow(digits / 2) == block).then_some((block, 2))
     }
 }
 
@@ -46,12 +102,17 @@ mod part2 {
     }
 
     fn is_invalid(n: usize) -> bool {
+        get_repetition(n).is_some()
+    }
+
+    // Returns the smallest repeating block and how many times it appears
+    pub(super) fn get_repetition(n: usize) -> Option<(usize, u32)> {
         let digits = n.ilog10() + 1;
         if digits < 2 {
-            return false;
+            return None;
         }
         for part_size in 1..=digits / 2 {
-            if digits % part_size != 0 {
+            if !digits.is_multiple_of(part_size) {
                 continue;
             }
             let mut parts = (0..digits / part_size).map(|part_number| {
@@ -62,14 +123,14 @@ mod part2 {
             // We check if all parts are equal
             let first = parts.next().unwrap();
             if parts.all(|part| part == first) {
-                return true;
+                return Some((first, digits / part_size));
             }
         }
-        false
+        None
     }
 }
 
-fn read_ranges(input: &str) -> Vec<Range<usize>> {
+fn read_input_ranges(input: &str) -> Vec<Range<usize>> {
     input
         .trim()
         .split(",")
@@ -80,6 +141,20 @@ fn read_ranges(input: &str) -> Vec<Range<usize>> {
         .collect()
 }
 
+fn read_ranges(input: &str) -> Vec<Range<usize>> {
+    let mut ranges = read_input_ranges(input);
+    ranges.sort_by_key(|range| range.start);
+    // Overlapping or duplicate ranges are merged so no ID is counted twice
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,4 +184,60 @@ mod tests {
         let result = part2::day02(&contents);
         assert_eq!(result, 44143124633);
     }
+
+    #[test]
+    fn overlapping_ranges_are_counted_once() {
+        let result = part1::day02("11-22,11-22,15-33");
+        assert_eq!(result, 11 + 22 + 33);
+        let result = part2::day02("95-115,99-111");
+        assert_eq!(result, 99 + 111);
+    }
+
+    #[test]
+    fn report_lists_invalid_ids_with_blocks() {
+        let report = report("95-115,222220-222224", part2::get_repetition);
+        assert_eq!(
+            report,
+            vec![
+                RangeReport {
+                    range: 95..116,
+                    invalid_ids: vec![
+                        InvalidId {
+                            id: 99,
+                            block: 9,
+                            repeats: 2
+                        },
+                        InvalidId {
+                            id: 111,
+                            block: 1,
+                            repeats: 3
+                        },
+                    ],
+                },
+                RangeReport {
+                    range: 222220..222225,
+                    invalid_ids: vec![InvalidId {
+                        id: 222222,
+                        block: 2,
+                        repeats: 6
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn report_keeps_input_ranges() {
+        let report = report("15-33,11-22", part1::get_repetition);
+        let ranges = report
+            .iter()
+            .map(|range_report| range_report.range.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![15..34, 11..23]);
+        let ids = report
+            .iter()
+            .map(|range_report| range_report.invalid_ids.len())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 2]);
+    }
 }
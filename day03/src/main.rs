@@ -1,60 +1,478 @@
-use std::fs;
+use std::{
+    cmp::Ordering,
+    env,
+    fmt::{self, Display},
+    fs,
+    iter::Sum,
+    ops::Add,
+};
 
 fn main() {
     let contents = fs::read_to_string("input").unwrap();
-    let result = day03_part1(&contents);
-    println!("Day03 part 1 result: {result}");
-    let result = day03_part2(&contents);
-    println!("Day03 part 2 result: {result}");
+    let variant = match env::args().skip_while(|arg| arg != "--variant").nth(1) {
+        Some(arg) => match read_variant(&arg) {
+            Some(variant) => variant,
+            None => {
+                return println!(
+                    "Day03 error: unknown variant {arg:?}, expected smallest, min-gap=N, \
+                     max-span=N or kth=N"
+                );
+            }
+        },
+        None => Variant::Largest,
+    };
+    if env::args().any(|arg| arg == "--show-selection") {
+        let banks = match read_battery_banks(&contents) {
+            Ok(banks) => banks,
+            Err(error) => return println!("Day03 error: {error}"),
+        };
+        for (part, batteries_on) in [(1, 2), (2, 12)] {
+            println!("Day03 part {part} selections:");
+            for bank in &banks {
+                match bank.get_selection(batteries_on, variant) {
+                    Some(selection) => println!(
+                        "{} = {}",
+                        bank.highlight(&selection.indices),
+                        selection.joltage
+                    ),
+                    None => println!("{} = no selection", bank.highlight(&[])),
+                }
+            }
+        }
+    }
+    if variant != Variant::Largest {
+        for (part, batteries_on) in [(1, 2), (2, 12)] {
+            match day03(&contents, batteries_on, variant) {
+                Ok(Some(result)) => println!("Day03 part {part} {variant:?} result: {result}"),
+                Ok(None) => println!("Day03 part {part} {variant:?} has no valid selection"),
+                Err(error) => println!("Day03 part {part} error: {error}"),
+            }
+        }
+        return;
+    }
+    match day03_part1(&contents) {
+        Ok(result) => println!("Day03 part 1 result: {result}"),
+        Err(error) => println!("Day03 part 1 error: {error}"),
+    }
+    match day03_part2(&contents) {
+        Ok(result) => println!("Day03 part 2 result: {result}"),
+        Err(error) => println!("Day03 part 2 error: {error}"),
+    }
 }
 
-fn day03_part1(input: &str) -> usize {
-    read_battery_banks(input)
+fn day03_part1(input: &str) -> Result<Joltage, BankError> {
+    read_battery_banks(input)?
         .iter()
         .map(|battery| battery.get_largest_joltage(2))
         .sum()
 }
 
-fn day03_part2(input: &str) -> usize {
-    read_battery_banks(input)
+fn day03_part2(input: &str) -> Result<Joltage, BankError> {
+    read_battery_banks(input)?
         .iter()
         .map(|battery| battery.get_largest_joltage(12))
         .sum()
 }
 
+fn day03(input: &str, batteries_on: usize, variant: Variant) -> Result<Option<Joltage>, BankError> {
+    let banks = read_battery_banks(input)?;
+    for bank in &banks {
+        bank.check_length(batteries_on)?;
+    }
+    Ok(banks
+        .iter()
+        .map(|battery| {
+            battery
+                .get_selection(batteries_on, variant)
+                .map(|selection| selection.joltage)
+        })
+        .sum())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Variant {
+    Largest,
+    Smallest,
+    MinGap(usize),
+    MaxSpan(usize),
+    KthLargest(usize),
+}
+
+fn read_variant(input: &str) -> Option<Variant> {
+    let (name, value) = match input.split_once('=') {
+        Some((name, value)) => (name, Some(value.parse().ok()?)),
+        None => (input, None),
+    };
+    match (name, value) {
+        ("largest", None) => Some(Variant::Largest),
+        ("smallest", None) => Some(Variant::Smallest),
+        ("min-gap", Some(gap)) if gap > 0 => Some(Variant::MinGap(gap)),
+        ("max-span", Some(span)) => Some(Variant::MaxSpan(span)),
+        ("kth", Some(k)) if k > 0 => Some(Variant::KthLargest(k)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum BankError {
+    InvalidBattery {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    NotEnoughBatteries {
+        line: usize,
+        batteries: usize,
+        batteries_on: usize,
+    },
+}
+
+impl Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BankError::InvalidBattery {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "invalid battery {found:?} at line {line}, column {column}"
+            ),
+            BankError::NotEnoughBatteries {
+                line,
+                batteries,
+                batteries_on,
+            } => write!(
+                f,
+                "bank at line {line} has {batteries} batteries but {batteries_on} must be on"
+            ),
+        }
+    }
+}
+
 struct BatteryBank {
+    line: usize,
     batteries: Vec<u8>,
 }
 
 impl BatteryBank {
-    fn get_largest_joltage(&self, batteries_on: usize) -> usize {
-        let mut joltage = 0;
-        let mut available_batteries = self.batteries.as_slice();
-        for remaning_choices in (0..batteries_on).rev() {
-            let new_digit = *available_batteries[0..available_batteries.len() - remaning_choices]
-                .iter()
-                .max()
+    fn get_largest_joltage(&self, batteries_on: usize) -> Result<Joltage, BankError> {
+        self.check_length(batteries_on)?;
+        Ok(self.get_largest_selection(batteries_on).joltage)
+    }
+
+    fn check_length(&self, batteries_on: usize) -> Result<(), BankError> {
+        if self.batteries.len() < batteries_on {
+            return Err(BankError::NotEnoughBatteries {
+                line: self.line,
+                batteries: self.batteries.len(),
+                batteries_on,
+            });
+        }
+        Ok(())
+    }
+
+    fn get_largest_selection(&self, batteries_on: usize) -> Selection {
+        self.get_extreme_selection(&self.batteries, batteries_on, Ordering::Less)
+    }
+
+    fn get_selection(&self, batteries_on: usize, variant: Variant) -> Option<Selection> {
+        if batteries_on > self.batteries.len() {
+            return None;
+        }
+        match variant {
+            Variant::Largest => Some(self.get_largest_selection(batteries_on)),
+            Variant::Smallest => {
+                Some(self.get_extreme_selection(&self.batteries, batteries_on, Ordering::Greater))
+            }
+            Variant::MinGap(gap) => self.get_largest_selection_with_gap(batteries_on, gap),
+            Variant::MaxSpan(span) => self.get_largest_selection_with_span(batteries_on, span),
+            Variant::KthLargest(k) => self.get_kth_largest_selection(batteries_on, k),
+        }
+    }
+
+    // Selection made of the indices picked by `get_extreme_indices`
+    fn get_extreme_selection(
+        &self,
+        batteries: &[u8],
+        batteries_on: usize,
+        replace_when: Ordering,
+    ) -> Selection {
+        let mut indices = Vec::with_capacity(batteries.len());
+        get_extreme_indices(batteries, batteries_on, replace_when, &mut indices);
+        self.to_selection(indices)
+    }
+
+    fn get_largest_selection_with_gap(&self, batteries_on: usize, gap: usize) -> Option<Selection> {
+        if batteries_on == 0 {
+            return Some(self.to_selection(Vec::new()));
+        }
+        let needed = (batteries_on - 1).checked_mul(gap)?;
+        if needed >= self.batteries.len() {
+            return None;
+        }
+        let mut indices = Vec::with_capacity(batteries_on);
+        let mut start = 0;
+        for remaining_choices in (0..batteries_on).rev() {
+            let end = self.batteries.len() - remaining_choices * gap;
+            // The first instance of the max leaves the most room for the next choices
+            let index = (start..end)
+                .rev()
+                .max_by_key(|&index| self.batteries[index])
                 .unwrap();
-            // Ensure we get the first instance of the max number
-            let new_digit_index = available_batteries
+            indices.push(index);
+            start = index.saturating_add(gap);
+        }
+        Some(self.to_selection(indices))
+    }
+
+    // Runs a stack selection on every window, so it takes O(n * span) time. The buffers are
+    // reused and selections of the same length compare digit by digit, so windows don't allocate.
+    fn get_largest_selection_with_span(
+        &self,
+        batteries_on: usize,
+        span: usize,
+    ) -> Option<Selection> {
+        let window = span.saturating_add(1);
+        if batteries_on > window || batteries_on > self.batteries.len() {
+            return None;
+        }
+        let window = window.min(self.batteries.len());
+        let mut indices = Vec::with_capacity(window);
+        let mut best: Option<(usize, Vec<usize>)> = None;
+        for start in 0..=self.batteries.len() - window {
+            let batteries = &self.batteries[start..start + window];
+            get_extreme_indices(batteries, batteries_on, Ordering::Less, &mut indices);
+            let is_better = best.as_ref().is_none_or(|(best_start, best_indices)| {
+                let digits = indices.iter().map(|&index| batteries[index]);
+                let best_digits = best_indices
+                    .iter()
+                    .map(|&index| self.batteries[best_start + index]);
+                digits.gt(best_digits)
+            });
+            if is_better {
+                best = Some((start, indices.clone()));
+            }
+        }
+        best.map(|(start, indices)| {
+            self.to_selection(indices.into_iter().map(|index| index + start).collect())
+        })
+    }
+
+    fn get_kth_largest_selection(&self, batteries_on: usize, k: usize) -> Option<Selection> {
+        let n = self.batteries.len();
+        let next = self.get_next_occurrences();
+        // counts[r][i] is the number of distinct joltages made of `r` batteries taken from `i`
+        // onwards. It is capped at `k`, as larger counts all lead to the same choices.
+        let mut counts = vec![vec![1_usize; n + 1]];
+        for r in 1..batteries_on {
+            let previous = &counts[r - 1];
+            let layer = next
                 .iter()
-                .position(|&n| n == new_digit)
-                .unwrap();
-            joltage = joltage * 10 + new_digit as usize;
-            available_batteries = &available_batteries[new_digit_index + 1..];
+                .map(|occurrences| {
+                    occurrences
+                        .iter()
+                        .filter(|&&index| index < n)
+                        .fold(0, |count: usize, &index| {
+                            count.saturating_add(previous[index + 1]).min(k)
+                        })
+                })
+                .collect();
+            counts.push(layer);
         }
-        joltage
+        let mut k = k;
+        let mut indices = Vec::with_capacity(batteries_on);
+        let mut start = 0;
+        for remaining_choices in (0..batteries_on).rev() {
+            let mut chosen = None;
+            for digit in (0..=9).rev() {
+                let index = next[start][digit];
+                if index == n {
+                    continue;
+                }
+                let count = counts[remaining_choices][index + 1];
+                if k <= count {
+                    chosen = Some(index);
+                    break;
+                }
+                k -= count;
+            }
+            let index = chosen?;
+            indices.push(index);
+            start = index + 1;
+        }
+        Some(self.to_selection(indices))
+    }
+
+    // First index at or after every position holding each digit, or the bank length if there is
+    // none
+    fn get_next_occurrences(&self) -> Vec<[usize; 10]> {
+        let n = self.batteries.len();
+        let mut next = vec![[n; 10]; n + 1];
+        for index in (0..n).rev() {
+            next[index] = next[index + 1];
+            next[index][self.batteries[index] as usize] = index;
+        }
+        next
+    }
+
+    fn to_selection(&self, indices: Vec<usize>) -> Selection {
+        let digits: Vec<u8> = indices.iter().map(|&index| self.batteries[index]).collect();
+        Selection {
+            joltage: Joltage::from_digits(&digits),
+            indices,
+        }
+    }
+
+    fn highlight(&self, indices: &[usize]) -> String {
+        let mut indices = indices.iter().peekable();
+        self.batteries
+            .iter()
+            .enumerate()
+            .map(|(index, battery)| {
+                if indices.next_if_eq(&&index).is_some() {
+                    format!("[{battery}]")
+                } else {
+                    battery.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Selection {
+    indices: Vec<usize>,
+    joltage: Joltage,
+}
+
+// Arbitrary precision number, stored as decimal digits from least to most significant
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Joltage {
+    digits: Vec<u8>,
+}
+
+impl Joltage {
+    fn from_digits(most_significant_first: &[u8]) -> Self {
+        let mut digits: Vec<u8> = most_significant_first.iter().rev().copied().collect();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Joltage { digits }
+    }
+}
+
+impl From<u64> for Joltage {
+    fn from(mut value: u64) -> Self {
+        let mut digits = Vec::new();
+        while value > 0 {
+            digits.push((value % 10) as u8);
+            value /= 10;
+        }
+        Joltage { digits }
+    }
+}
+
+impl Add for Joltage {
+    type Output = Joltage;
+
+    fn add(self, other: Joltage) -> Joltage {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry = 0;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = self.digits.get(i).unwrap_or(&0) + other.digits.get(i).unwrap_or(&0) + carry;
+            digits.push(sum % 10);
+            carry = sum / 10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+        Joltage { digits }
     }
 }
 
-fn read_battery_banks(input: &str) -> Vec<BatteryBank> {
+impl Ord for Joltage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for Joltage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Sum for Joltage {
+    fn sum<I: Iterator<Item = Joltage>>(iter: I) -> Self {
+        iter.fold(Joltage::default(), |total, joltage| total + joltage)
+    }
+}
+
+impl Display for Joltage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        for digit in self.digits.iter().rev() {
+            write!(f, "{digit}")?;
+        }
+        Ok(())
+    }
+}
+
+// Fills `indices` with the positions of the `batteries_on` digits of `batteries` that make the
+// largest number when the ordering is `Less` and the smallest one when it is `Greater`
+fn get_extreme_indices(
+    batteries: &[u8],
+    batteries_on: usize,
+    replace_when: Ordering,
+    indices: &mut Vec<usize>,
+) {
+    indices.clear();
+    let mut removals_left = batteries.len() - batteries_on;
+    for (index, &battery) in batteries.iter().enumerate() {
+        // Replacing an earlier digit with a better one always improves the joltage
+        while removals_left > 0
+            && indices
+                .last()
+                .is_some_and(|&last| batteries[last].cmp(&battery) == replace_when)
+        {
+            indices.pop();
+            removals_left -= 1;
+        }
+        indices.push(index);
+    }
+    indices.truncate(batteries_on);
+}
+
+fn read_battery_banks(input: &str) -> Result<Vec<BatteryBank>, BankError> {
     input
         .lines()
-        .map(|line| BatteryBank {
-            batteries: line
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| {
+            let batteries = line
                 .chars()
-                .map(|c| c.to_digit(10).unwrap() as u8)
-                .collect(),
+                .enumerate()
+                .map(|(column, c)| {
+                    c.to_digit(10)
+                        .map(|digit| digit as u8)
+                        .ok_or(BankError::InvalidBattery {
+                            line: line_number,
+                            column: column + 1,
+                            found: c,
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(BatteryBank {
+                line: line_number,
+                batteries,
+            })
         })
         .collect()
 }
@@ -65,28 +483,245 @@ mod tests {
     #[test]
     fn part1_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day03_part1(&contents);
-        assert_eq!(result, 357);
+        let result = day03_part1(&contents).unwrap();
+        assert_eq!(result, Joltage::from(357));
     }
 
     #[test]
     fn part1_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day03_part1(&contents);
-        assert_eq!(result, 17193);
+        let result = day03_part1(&contents).unwrap();
+        assert_eq!(result, Joltage::from(17193));
     }
 
     #[test]
     fn part2_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day03_part2(&contents);
-        assert_eq!(result, 3121910778619);
+        let result = day03_part2(&contents).unwrap();
+        assert_eq!(result, Joltage::from(3121910778619));
     }
 
     #[test]
     fn part2_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day03_part2(&contents);
-        assert_eq!(result, 171297349921310);
+        let result = day03_part2(&contents).unwrap();
+        assert_eq!(result, Joltage::from(171297349921310));
+    }
+
+    fn get_largest_joltage_greedy(bank: &BatteryBank, batteries_on: usize) -> u64 {
+        let mut joltage = 0;
+        let mut available_batteries = bank.batteries.as_slice();
+        for remaning_choices in (0..batteries_on).rev() {
+            let new_digit = *available_batteries[0..available_batteries.len() - remaning_choices]
+                .iter()
+                .max()
+                .unwrap();
+            let new_digit_index = available_batteries
+                .iter()
+                .position(|&n| n == new_digit)
+                .unwrap();
+            joltage = joltage * 10 + new_digit as u64;
+            available_batteries = &available_batteries[new_digit_index + 1..];
+        }
+        joltage
+    }
+
+    #[test]
+    fn stack_selection_matches_greedy_selection() {
+        for file in ["test_input", "input"] {
+            let contents = fs::read_to_string(file).unwrap();
+            for bank in read_battery_banks(&contents).unwrap() {
+                for batteries_on in 1..=bank.batteries.len().min(19) {
+                    assert_eq!(
+                        bank.get_largest_joltage(batteries_on).unwrap(),
+                        Joltage::from(get_largest_joltage_greedy(&bank, batteries_on))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_selections_do_not_overflow() {
+        let bank = BatteryBank {
+            line: 1,
+            batteries: "9".repeat(60).bytes().map(|c| c - b'0').collect(),
+        };
+        let joltage = bank.get_largest_joltage(50).unwrap();
+        assert_eq!(joltage.to_string(), "9".repeat(50));
+        let total: Joltage = [joltage.clone(), joltage].into_iter().sum();
+        assert_eq!(total.to_string(), format!("1{}8", "9".repeat(49)));
+    }
+
+    #[test]
+    fn selection_returns_chosen_indices() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let banks = read_battery_banks(&contents).unwrap();
+        let selection = banks[1].get_largest_selection(2);
+        assert_eq!(selection.indices, vec![0, 14]);
+        assert_eq!(selection.joltage, Joltage::from(89));
+        assert_eq!(
+            banks[1].highlight(&selection.indices),
+            "[8]1111111111111[9]"
+        );
+    }
+
+    fn brute_force_joltages(
+        bank: &BatteryBank,
+        batteries_on: usize,
+        is_valid: impl Fn(&[usize]) -> bool,
+    ) -> Vec<u64> {
+        let n = bank.batteries.len();
+        let mut joltages: Vec<u64> = (0..1u32 << n)
+            .filter(|mask| mask.count_ones() as usize == batteries_on)
+            .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect::<Vec<_>>())
+            .filter(|indices| is_valid(indices))
+            .map(|indices| {
+                indices
+                    .iter()
+                    .fold(0, |joltage, &i| joltage * 10 + bank.batteries[i] as u64)
+            })
+            .collect();
+        joltages.sort_unstable_by(|a, b| b.cmp(a));
+        joltages.dedup();
+        joltages
+    }
+
+    fn test_banks() -> Vec<BatteryBank> {
+        let contents = fs::read_to_string("test_input").unwrap();
+        read_battery_banks(&contents).unwrap()
+    }
+
+    #[test]
+    fn smallest_variant_matches_brute_force() {
+        for bank in test_banks() {
+            for batteries_on in 1..=4 {
+                let expected = *brute_force_joltages(&bank, batteries_on, |_| true)
+                    .last()
+                    .unwrap();
+                let selection = bank.get_selection(batteries_on, Variant::Smallest).unwrap();
+                assert_eq!(selection.joltage, Joltage::from(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn huge_gaps_and_spans_do_not_overflow() {
+        let bank = BatteryBank {
+            line: 1,
+            batteries: vec![9, 1, 8, 2],
+        };
+        assert_eq!(bank.get_selection(2, Variant::MinGap(usize::MAX)), None);
+        let single = bank.get_selection(1, Variant::MinGap(usize::MAX)).unwrap();
+        assert_eq!(single.indices, vec![0]);
+        let spread = bank.get_selection(2, Variant::MaxSpan(usize::MAX)).unwrap();
+        assert_eq!(spread.indices, vec![0, 2]);
+        assert_eq!(bank.get_selection(5, Variant::MaxSpan(usize::MAX)), None);
+    }
+
+    #[test]
+    fn min_gap_variant_matches_brute_force() {
+        for bank in test_banks() {
+            for (batteries_on, gap) in [(2, 3), (3, 2), (4, 4), (4, 5)] {
+                let expected = brute_force_joltages(&bank, batteries_on, |indices| {
+                    indices.windows(2).all(|pair| pair[1] - pair[0] >= gap)
+                })
+                .first()
+                .map(|&joltage| Joltage::from(joltage));
+                let selection = bank.get_selection(batteries_on, Variant::MinGap(gap));
+                assert_eq!(selection.map(|selection| selection.joltage), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn max_span_variant_matches_brute_force() {
+        for bank in test_banks() {
+            for (batteries_on, span) in [(2, 1), (3, 4), (4, 6), (4, 2)] {
+                let expected = brute_force_joltages(&bank, batteries_on, |indices| {
+                    indices.last().unwrap() - indices.first().unwrap() <= span
+                })
+                .first()
+                .map(|&joltage| Joltage::from(joltage));
+                let selection = bank.get_selection(batteries_on, Variant::MaxSpan(span));
+                assert_eq!(selection.map(|selection| selection.joltage), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn kth_largest_variant_matches_brute_force() {
+        for bank in test_banks() {
+            for batteries_on in 1..=4 {
+                let joltages = brute_force_joltages(&bank, batteries_on, |_| true);
+                for k in 1..=joltages.len() + 1 {
+                    let expected = joltages.get(k - 1).map(|&joltage| Joltage::from(joltage));
+                    let selection = bank.get_selection(batteries_on, Variant::KthLargest(k));
+                    assert_eq!(selection.map(|selection| selection.joltage), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kth_largest_handles_long_banks() {
+        let bank = BatteryBank {
+            line: 1,
+            batteries: (0..20_000).map(|i| (i * 7 % 10) as u8).collect(),
+        };
+        let largest = bank.get_largest_selection(200);
+        let first = bank.get_selection(200, Variant::KthLargest(1)).unwrap();
+        assert_eq!(first.joltage, largest.joltage);
+        let second = bank.get_selection(200, Variant::KthLargest(2)).unwrap();
+        assert!(second.joltage < first.joltage);
+    }
+
+    #[test]
+    fn variants_are_read_from_arguments() {
+        assert_eq!(read_variant("smallest"), Some(Variant::Smallest));
+        assert_eq!(read_variant("min-gap=3"), Some(Variant::MinGap(3)));
+        assert_eq!(read_variant("max-span=10"), Some(Variant::MaxSpan(10)));
+        assert_eq!(read_variant("kth=2"), Some(Variant::KthLargest(2)));
+        assert_eq!(read_variant("kth=0"), None);
+        assert_eq!(read_variant("largest=1"), None);
+    }
+
+    #[test]
+    fn parser_skips_blank_lines_and_trailing_whitespace() {
+        let banks = read_battery_banks("12345  \n\n987\t\n").unwrap();
+        assert_eq!(banks.len(), 2);
+        assert_eq!(banks[0].batteries, vec![1, 2, 3, 4, 5]);
+        assert_eq!(banks[1].line, 3);
+        assert_eq!(banks[1].batteries, vec![9, 8, 7]);
+    }
+
+    #[test]
+    fn parser_rejects_invalid_batteries() {
+        let result = read_battery_banks("123\n45x6\n");
+        assert_eq!(
+            result.err(),
+            Some(BankError::InvalidBattery {
+                line: 2,
+                column: 3,
+                found: 'x'
+            })
+        );
+    }
+
+    #[test]
+    fn short_banks_are_reported() {
+        let result = day03_part2("987654321111111\n12345\n");
+        assert_eq!(
+            result,
+            Err(BankError::NotEnoughBatteries {
+                line: 2,
+                batteries: 5,
+                batteries_on: 12
+            })
+        );
+        assert_eq!(
+            day03_part1("98\n7\n").unwrap_err().to_string(),
+            "bank at line 2 has 1 batteries but 2 must be on"
+        );
     }
 }
@@ -0,0 +1,216 @@
+use std::str::FromStr;
+
+use crate::{Cell, Matrix, Neighbourhood, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Counts {
+    Exactly(Vec<usize>),
+    AtLeast(usize),
+}
+
+impl Counts {
+    fn contains(&self, count: usize) -> bool {
+        match self {
+            Counts::Exactly(counts) => counts.contains(&count),
+            Counts::AtLeast(min) => count >= *min,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub birth: Counts,
+    pub survival: Counts,
+}
+
+impl Rule {
+    // Paper stays only while it has at least `threshold` paper neighbours and never reappears
+    pub fn threshold(threshold: usize) -> Self {
+        Rule {
+            birth: Counts::Exactly(Vec::new()),
+            survival: Counts::AtLeast(threshold),
+        }
+    }
+
+    fn next(&self, cell: Cell, neighbours: usize) -> Cell {
+        let alive = match cell {
+            Cell::Paper => self.survival.contains(neighbours),
+            Cell::Empty => self.birth.contains(neighbours),
+        };
+        if alive { Cell::Paper } else { Cell::Empty }
+    }
+}
+
+// Life-like rules written as e.g. "B3/S23"
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let read_counts = |part: &str, prefix: char| {
+            part.strip_prefix(prefix)
+                .ok_or(format!("expected '{prefix}' in {part:?}"))?
+                .chars()
+                .map(|c| {
+                    c.to_digit(10)
+                        .map(|digit| digit as usize)
+                        .ok_or(format!("invalid neighbour count {c:?}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Counts::Exactly)
+        };
+        let (birth, survival) = input
+            .split_once('/')
+            .ok_or(format!("expected B<counts>/S<counts>, found {input:?}"))?;
+        Ok(Rule {
+            birth: read_counts(birth, 'B')?,
+            survival: read_counts(survival, 'S')?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    Synchronous,
+    // Cells are updated in place, in reading order, so later cells see earlier changes
+    Asynchronous,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    FixedPoint,
+    Generations(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    pub rule: Rule,
+    pub update: Update,
+    pub stop: Stop,
+    pub neighbourhood: Neighbourhood,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Run {
+    pub generations: usize,
+    pub born: usize,
+    pub died: usize,
+}
+
+impl Automaton {
+    pub fn run(&self, matrix: &mut Matrix<Cell>) -> Run {
+        self.run_with(matrix, |_, _, _| {})
+    }
+
+    // Calls `on_generation` with the generation number, the updated matrix and the cells that
+    // changed after every generation
+    pub fn run_with(
+        &self,
+        matrix: &mut Matrix<Cell>,
+        on_generation: impl FnMut(usize, &Matrix<Cell>, &[Position]),
+    ) -> Run {
+        match self.update {
+            Update::Synchronous => self.run_synchronous(matrix, on_generation),
+            Update::Asynchronous => self.run_asynchronous(matrix, on_generation),
+        }
+    }
+
+    fn is_done(&self, run: &Run) -> bool {
+        self.stop == Stop::Generations(run.generations)
+    }
+
+    // Keeps the paper neighbour count of every cell up to date and only re-examines the cells
+    // around the last changes, as nothing else can change in the next generation
+    fn run_synchronous(
+        &self,
+        matrix: &mut Matrix<Cell>,
+        mut on_generation: impl FnMut(usize, &Matrix<Cell>, &[Position]),
+    ) -> Run {
+        let mut run = Run::default();
+        let mut neighbours = matrix.map(|pos| self.count_paper_neighbours(matrix, pos));
+        let mut last_queued = matrix.map(|_| 0);
+        let mut candidates = matrix.iter().collect::<Vec<Position>>();
+        while !self.is_done(&run) {
+            let changes = candidates
+                .iter()
+                .filter_map(|&pos| {
+                    let next = self.rule.next(matrix[pos], neighbours[pos]);
+                    (next != matrix[pos]).then_some((pos, next))
+                })
+                .collect::<Vec<(Position, Cell)>>();
+            if changes.is_empty() {
+                break;
+            }
+            run.generations += 1;
+            candidates.clear();
+            for &(pos, cell) in &changes {
+                matrix[pos] = cell;
+                match cell {
+                    Cell::Paper => run.born += 1,
+                    Cell::Empty => run.died += 1,
+                }
+                let around = matrix.get_neighbors(pos, &self.neighbourhood);
+                for &neighbour in &around {
+                    match cell {
+                        Cell::Paper => neighbours[neighbour] += 1,
+                        Cell::Empty => neighbours[neighbour] -= 1,
+                    }
+                }
+                for candidate in around.into_iter().chain([pos]) {
+                    if last_queued[candidate] != run.generations {
+                        last_queued[candidate] = run.generations;
+                        candidates.push(candidate);
+                    }
+                }
+            }
+            let changed = changes.into_iter().map(|(pos, _)| pos).collect::<Vec<_>>();
+            on_generation(run.generations, matrix, &changed);
+        }
+        run
+    }
+
+    fn run_asynchronous(
+        &self,
+        matrix: &mut Matrix<Cell>,
+        mut on_generation: impl FnMut(usize, &Matrix<Cell>, &[Position]),
+    ) -> Run {
+        let mut run = Run::default();
+        while !self.is_done(&run) {
+            let changed = self.step_asynchronous(matrix);
+            if changed.is_empty() {
+                break;
+            }
+            run.generations += 1;
+            for &pos in &changed {
+                match matrix[pos] {
+                    Cell::Paper => run.born += 1,
+                    Cell::Empty => run.died += 1,
+                }
+            }
+            on_generation(run.generations, matrix, &changed);
+        }
+        run
+    }
+
+    fn step_asynchronous(&self, matrix: &mut Matrix<Cell>) -> Vec<Position> {
+        let mut changed = Vec::new();
+        let positions = matrix.iter().collect::<Vec<Position>>();
+        for pos in positions {
+            let next = self
+                .rule
+                .next(matrix[pos], self.count_paper_neighbours(matrix, pos));
+            if next != matrix[pos] {
+                matrix[pos] = next;
+                changed.push(pos);
+            }
+        }
+        changed
+    }
+
+    fn count_paper_neighbours(&self, matrix: &Matrix<Cell>, pos: Position) -> usize {
+        matrix
+            .get_neighbors(pos, &self.neighbourhood)
+            .into_iter()
+            .filter(|&neighbour| matrix[neighbour] == Cell::Paper)
+            .count()
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::BufRead;
+
+use crate::{MIN_PAPER_NEIGHBOURS, PaperGrid, automaton::Stop};
+
+// One bit per cell, 64 cells per word, always using the 8 surrounding cells as neighbours.
+// Every word of a row is handled at once by counting neighbours with bitwise adders.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BitGrid {
+    rows: usize,
+    columns: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl BitGrid {
+    // Lines are streamed one at a time, so only the bits of the grid are held in memory
+    pub fn read(reader: impl BufRead) -> Result<BitGrid, String> {
+        let mut grid = BitGrid {
+            rows: 0,
+            columns: 0,
+            words_per_row: 0,
+            bits: Vec::new(),
+        };
+        for line in reader.lines() {
+            let line = line.map_err(|error| error.to_string())?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if grid.rows == 0 {
+                grid.columns = line.len();
+                grid.words_per_row = grid.columns.div_ceil(64);
+            } else if line.len() != grid.columns {
+                return Err(format!(
+                    "row {} has {} cells instead of {}",
+                    grid.rows + 1,
+                    line.len(),
+                    grid.columns
+                ));
+            }
+            let offset = grid.bits.len();
+            grid.bits.resize(offset + grid.words_per_row, 0);
+            for (j, c) in line.bytes().enumerate() {
+                match c {
+                    b'@' => grid.bits[offset + j / 64] |= 1 << (j % 64),
+                    b'.' => {}
+                    _ => {
+                        return Err(format!(
+                            "invalid cell {:?} at row {}",
+                            c as char,
+                            grid.rows + 1
+                        ));
+                    }
+                }
+            }
+            grid.rows += 1;
+        }
+        Ok(grid)
+    }
+
+    fn row(&self, i: usize) -> &[u64] {
+        &self.bits[i * self.words_per_row..(i + 1) * self.words_per_row]
+    }
+
+    // Removes all the accessible paper in the rows marked as dirty at once. Returns the amount
+    // removed and the rows that can change in the next round.
+    fn remove_round(&mut self, dirty: &[bool]) -> (usize, Vec<bool>) {
+        let mut removed = 0;
+        let mut next_dirty = vec![false; self.rows];
+        let empty_row = vec![0; self.words_per_row];
+        // Rows are updated in place, so the row above is kept as it was before this round
+        let mut above = empty_row.clone();
+        let mut accessible = vec![0; self.words_per_row];
+        for (i, &is_dirty) in dirty.iter().enumerate() {
+            let current = self.row(i).to_vec();
+            if is_dirty {
+                let below = if i + 1 < self.rows {
+                    self.row(i + 1)
+                } else {
+                    &empty_row
+                };
+                for (w, word) in accessible.iter_mut().enumerate() {
+                    let mut planes = [0; 4];
+                    for row in [&above[..], below] {
+                        add(&mut planes, row[w]);
+                        add(&mut planes, west(row, w));
+                        add(&mut planes, east(row, w));
+                    }
+                    add(&mut planes, west(&current, w));
+                    add(&mut planes, east(&current, w));
+                    *word = current[w] & less_than(&planes, MIN_PAPER_NEIGHBOURS);
+                }
+                let removed_in_row: usize = accessible
+                    .iter()
+                    .map(|word| word.count_ones() as usize)
+                    .sum();
+                if removed_in_row > 0 {
+                    removed += removed_in_row;
+                    for (bits, word) in self.bits
+                        [i * self.words_per_row..(i + 1) * self.words_per_row]
+                        .iter_mut()
+                        .zip(&accessible)
+                    {
+                        *bits &= !word;
+                    }
+                    next_dirty[i.saturating_sub(1)..(i + 2).min(self.rows)].fill(true);
+                }
+            }
+            above = current;
+        }
+        (removed, next_dirty)
+    }
+}
+
+impl PaperGrid for BitGrid {
+    fn remove_accessible(&mut self, stop: Stop) -> usize {
+        let mut removed = 0;
+        let mut rounds = 0;
+        let mut dirty = vec![true; self.rows];
+        while stop != Stop::Generations(rounds) {
+            let (removed_in_round, next_dirty) = self.remove_round(&dirty);
+            if removed_in_round == 0 {
+                break;
+            }
+            removed += removed_in_round;
+            rounds += 1;
+            dirty = next_dirty;
+        }
+        removed
+    }
+}
+
+// Cells of `row` shifted one column to the right, so each bit holds its west neighbour
+fn west(row: &[u64], w: usize) -> u64 {
+    let carry = if w > 0 { row[w - 1] >> 63 } else { 0 };
+    (row[w] << 1) | carry
+}
+
+// Cells of `row` shifted one column to the left, so each bit holds its east neighbour
+fn east(row: &[u64], w: usize) -> u64 {
+    let carry = if w + 1 < row.len() {
+        row[w + 1] << 63
+    } else {
+        0
+    };
+    (row[w] >> 1) | carry
+}
+
+// Adds one bit to each of the 64 counters stored across the planes, least significant first
+fn add(planes: &mut [u64; 4], bits: u64) {
+    let mut carry = bits;
+    for plane in planes.iter_mut() {
+        let next_carry = *plane & carry;
+        *plane ^= carry;
+        carry = next_carry;
+    }
+}
+
+fn less_than(planes: &[u64; 4], threshold: usize) -> u64 {
+    (0..threshold.min(16))
+        .map(|value| {
+            planes.iter().enumerate().fold(!0, |equal, (bit, plane)| {
+                equal
+                    & if value >> bit & 1 == 1 {
+                        *plane
+                    } else {
+                        !plane
+                    }
+            })
+        })
+        .fold(0, |less, equal| less | equal)
+}
@@ -0,0 +1,61 @@
+use crate::{Matrix, image};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Depth {
+    Empty,
+    Removed(usize),
+    Never,
+}
+
+impl Matrix<Depth> {
+    fn max_round(&self) -> usize {
+        self.iter()
+            .filter_map(|pos| match self[pos] {
+                Depth::Removed(round) => Some(round),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // One column per cell, with `.` for empty cells and `#` for paper that is never removed
+    pub fn to_text(&self) -> String {
+        let width = self.max_round().to_string().len();
+        self.inner
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|depth| match depth {
+                        Depth::Empty => format!("{:>width$}", "."),
+                        Depth::Removed(round) => format!("{round:>width$}"),
+                        Depth::Never => format!("{:>width$}", "#"),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+                    + "\n"
+            })
+            .collect()
+    }
+
+    // Empty cells are black, paper gets brighter the later it is removed, up to white for
+    // paper that is never removed
+    pub fn to_pgm(&self) -> String {
+        let max_round = self.max_round();
+        image::to_pgm(self, |depth| match depth {
+            Depth::Empty => 0,
+            Depth::Removed(round) => (1 + (round - 1) * 200 / max_round.max(1)) as u8,
+            Depth::Never => 255,
+        })
+    }
+
+    // Empty cells are white, removed paper goes from yellow to red the later it is removed,
+    // and paper that is never removed is black
+    pub fn to_ppm(&self) -> String {
+        let max_round = self.max_round();
+        image::to_ppm(self, |depth| match depth {
+            Depth::Empty => [255, 255, 255],
+            Depth::Removed(round) => [255, (230 - (round - 1) * 230 / max_round.max(1)) as u8, 0],
+            Depth::Never => [0, 0, 0],
+        })
+    }
+}
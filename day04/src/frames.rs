@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+use crate::{Cell, Matrix, Position, image};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrameFormat {
+    Ascii,
+    Ppm,
+}
+
+impl FrameFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Ascii => "txt",
+            FrameFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl FromStr for FrameFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "ascii" => Ok(FrameFormat::Ascii),
+            "ppm" => Ok(FrameFormat::Ppm),
+            _ => Err(format!("unknown frame format {input:?}")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Pixel {
+    Paper,
+    Empty,
+    Removed,
+}
+
+// Draws the matrix with the paper removed in the last round highlighted, as `x` in ASCII
+// frames and in red in PPM frames
+pub fn render(matrix: &Matrix<Cell>, removed: &[Position], format: FrameFormat) -> String {
+    let mut pixels = matrix.map(|pos| match matrix[pos] {
+        Cell::Paper => Pixel::Paper,
+        Cell::Empty => Pixel::Empty,
+    });
+    for &pos in removed {
+        pixels[pos] = Pixel::Removed;
+    }
+    match format {
+        FrameFormat::Ascii => pixels
+            .inner
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| match pixel {
+                        Pixel::Paper => '@',
+                        Pixel::Empty => '.',
+                        Pixel::Removed => 'x',
+                    })
+                    .chain(['\n'])
+                    .collect::<String>()
+            })
+            .collect(),
+        FrameFormat::Ppm => image::to_ppm(&pixels, |pixel| match pixel {
+            Pixel::Paper => [64, 64, 64],
+            Pixel::Empty => [255, 255, 255],
+            Pixel::Removed => [220, 30, 30],
+        }),
+    }
+}
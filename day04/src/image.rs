@@ -0,0 +1,32 @@
+use crate::Matrix;
+
+// Plain (ASCII) PGM and PPM images, one pixel per cell
+
+pub fn to_pgm<T>(matrix: &Matrix<T>, grey: impl Fn(&T) -> u8) -> String {
+    let mut image = format!("P2\n{} {}\n255\n", matrix.n(), matrix.m());
+    for row in &matrix.inner {
+        let pixels = row
+            .iter()
+            .map(|cell| grey(cell).to_string())
+            .collect::<Vec<String>>();
+        image.push_str(&pixels.join(" "));
+        image.push('\n');
+    }
+    image
+}
+
+pub fn to_ppm<T>(matrix: &Matrix<T>, colour: impl Fn(&T) -> [u8; 3]) -> String {
+    let mut image = format!("P3\n{} {}\n255\n", matrix.n(), matrix.m());
+    for row in &matrix.inner {
+        let pixels = row
+            .iter()
+            .map(|cell| {
+                let [r, g, b] = colour(cell);
+                format!("{r} {g} {b}")
+            })
+            .collect::<Vec<String>>();
+        image.push_str(&pixels.join(" "));
+        image.push('\n');
+    }
+    image
+}
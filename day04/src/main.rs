@@ -1,8 +1,25 @@
+mod automaton;
+mod bit_grid;
+mod depth_map;
+mod frames;
+mod image;
+mod sparse_grid;
+
 use std::{
-    fs,
+    env,
+    fmt::Display,
+    fs::{self, File},
+    io::{BufRead, BufReader},
     ops::{Index, IndexMut},
+    str::FromStr,
 };
 
+use automaton::{Automaton, Rule, Stop, Update};
+use bit_grid::BitGrid;
+use depth_map::Depth;
+use frames::FrameFormat;
+use sparse_grid::SparseGrid;
+
 use nom::{
     IResult, Parser,
     branch::alt,
@@ -13,54 +30,243 @@ use nom::{
 };
 
 fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    let shape = match parse_option(&args, "--neighbourhood") {
+        Ok(shape) => shape.unwrap_or(Shape::Moore(1)),
+        Err(error) => return println!("Day04 error: {error}"),
+    };
+    let neighbourhood = Neighbourhood {
+        shape,
+        wrap: args.iter().any(|arg| arg == "--wrap"),
+    };
+    // The bits backend is meant for grids whose text doesn't fit in memory, so the input is
+    // streamed instead of read into a string and only both parts are answered
+    if get_option(&args, "--backend") == Some("bits") {
+        let grid = check_bits_options(&args)
+            .and_then(|_| File::open("input").map_err(|error| error.to_string()))
+            .and_then(|file| read_bit_grid(BufReader::new(file), neighbourhood));
+        match grid {
+            Ok(mut grid) => {
+                let result = day04_part1(&mut grid.clone());
+                println!("Day04 part 1 result: {result}");
+                let result = day04_part2(&mut grid);
+                println!("Day04 part 2 result: {result}");
+            }
+            Err(error) => println!("Day04 error: {error}"),
+        }
+        return;
+    }
     let contents = fs::read_to_string("input").unwrap();
-    let result = day04_part1(&contents);
+    if let Some(rule) = get_option(&args, "--rule") {
+        let options = rule
+            .parse::<Rule>()
+            .map_err(|error| format!("--rule: {error}"))
+            .and_then(|parsed| Ok((parsed, parse_option(&args, "--generations")?)));
+        let (parsed_rule, generations) = match options {
+            Ok(options) => options,
+            Err(error) => return println!("Day04 error: {error}"),
+        };
+        let automaton = Automaton {
+            rule: parsed_rule,
+            update: if args.iter().any(|arg| arg == "--async") {
+                Update::Asynchronous
+            } else {
+                Update::Synchronous
+            },
+            stop: generations
+                .map(Stop::Generations)
+                .unwrap_or(Stop::FixedPoint),
+            neighbourhood,
+        };
+        let mut matrix = match read_grid(&contents, neighbourhood) {
+            Ok(matrix) => matrix,
+            Err(error) => return println!("Day04 error: {error}"),
+        };
+        let run = automaton.run(&mut matrix);
+        let paper = matrix
+            .iter()
+            .filter(|&pos| matrix[pos] == Cell::Paper)
+            .count();
+        println!(
+            "Day04 {rule} ran {} generations: {} born, {} died, {paper} paper left",
+            run.generations, run.born, run.died
+        );
+        return;
+    }
+    if let Some(path) = get_option(&args, "--depth-map") {
+        let depths = match day04_depth_map(&contents, neighbourhood) {
+            Ok(depths) => depths,
+            Err(error) => return println!("Day04 error: {error}"),
+        };
+        let output = match path.rsplit_once('.') {
+            Some((_, "pgm")) => depths.to_pgm(),
+            Some((_, "ppm")) => depths.to_ppm(),
+            _ => depths.to_text(),
+        };
+        fs::write(path, output).unwrap();
+    }
+    if let Some(directory) = get_option(&args, "--frames") {
+        let format = match parse_option(&args, "--frame-format") {
+            Ok(format) => format.unwrap_or(FrameFormat::Ascii),
+            Err(error) => return println!("Day04 error: {error}"),
+        };
+        fs::create_dir_all(directory).unwrap();
+        let frames = day04_part2_with_frames(&contents, neighbourhood, format, |round, frame| {
+            let path = format!("{directory}/frame_{round:04}.{}", format.extension());
+            fs::write(path, frame).unwrap();
+        });
+        if let Err(error) = frames {
+            return println!("Day04 error: {error}");
+        }
+    }
+    let sparse_grid = match (
+        get_option(&args, "--coordinates"),
+        get_option(&args, "--backend"),
+    ) {
+        (Some(path), _) => {
+            let coordinates = fs::read_to_string(path).unwrap();
+            Some(SparseGrid::read_coordinates(&coordinates, neighbourhood).unwrap())
+        }
+        (None, Some("sparse")) => Some(SparseGrid::read_drawing(&contents, neighbourhood).unwrap()),
+        _ => None,
+    };
+    if let Some(grid) = sparse_grid {
+        let result = day04_part1(&mut grid.clone());
+        println!("Day04 part 1 result: {result}");
+        let result = day04_part2(&mut grid.clone());
+        println!("Day04 part 2 result: {result}");
+        return;
+    }
+    let grid = match DenseGrid::read(&contents, neighbourhood) {
+        Ok(grid) => grid,
+        Err(error) => return println!("Day04 error: {error}"),
+    };
+    let result = day04_part1(&mut grid.clone());
     println!("Day04 part 1 result: {result}");
-    let result = day04_part2(&contents);
+    let result = day04_part2(&mut grid.clone());
     println!("Day04 part 2 result: {result}");
 }
 
-fn day04_part1(input: &str) -> usize {
-    let (_, matrix) = read_input(input).unwrap();
-    matrix
-        .iter()
-        .filter(|&pos| matrix[pos] == Cell::Paper)
-        .filter(|&pos| {
-            matrix
-                .get_neighbors(pos)
-                .into_iter()
-                .filter(|&neighbour| matrix[neighbour] == Cell::Paper)
-                .count()
-                < 4
+// The bits backend only answers both parts, so any option that needs the dense grid is an error
+fn check_bits_options(args: &[String]) -> Result<(), String> {
+    let unsupported = [
+        "--rule",
+        "--async",
+        "--generations",
+        "--depth-map",
+        "--frames",
+        "--frame-format",
+        "--coordinates",
+    ];
+    match unsupported
+        .into_iter()
+        .find(|option| args.iter().any(|arg| arg == option))
+    {
+        Some(option) => Err(format!("the bits backend doesn't support {option}")),
+        None => Ok(()),
+    }
+}
+
+fn read_bit_grid(reader: impl BufRead, neighbourhood: Neighbourhood) -> Result<BitGrid, String> {
+    if neighbourhood != Neighbourhood::default() {
+        return Err("the bits backend only supports the default neighbourhood".to_string());
+    }
+    BitGrid::read(reader)
+}
+
+fn parse_option<T>(args: &[String], name: &str) -> Result<Option<T>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    get_option(args, name)
+        .map(|value| value.parse().map_err(|error| format!("{name}: {error}")))
+        .transpose()
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str())
+}
+
+fn day04_part1(grid: &mut impl PaperGrid) -> usize {
+    grid.remove_accessible(Stop::Generations(1))
+}
+
+fn day04_part2(grid: &mut impl PaperGrid) -> usize {
+    grid.remove_accessible(Stop::FixedPoint)
+}
+
+const MIN_PAPER_NEIGHBOURS: usize = 4;
+
+trait PaperGrid {
+    // Removes all the paper with fewer than `MIN_PAPER_NEIGHBOURS` paper neighbours at once,
+    // round after round, and returns how much was removed
+    fn remove_accessible(&mut self, stop: Stop) -> usize;
+}
+
+#[derive(Clone)]
+struct DenseGrid {
+    matrix: Matrix<Cell>,
+    neighbourhood: Neighbourhood,
+}
+
+impl DenseGrid {
+    fn read(input: &str, neighbourhood: Neighbourhood) -> Result<DenseGrid, String> {
+        Ok(DenseGrid {
+            matrix: read_grid(input, neighbourhood)?,
+            neighbourhood,
         })
-        .count()
+    }
 }
 
-fn day04_part2(input: &str) -> usize {
-    let (_, mut matrix) = read_input(input).unwrap();
-    let mut total_changes = 0;
-    loop {
-        let removed = matrix
-            .iter()
-            .filter(|&pos| {
-                matrix[pos] == Cell::Paper
-                    && matrix
-                        .get_neighbors(pos)
-                        .into_iter()
-                        .filter(|&neighbour| matrix[neighbour] == Cell::Paper)
-                        .count()
-                        < 4
-            })
-            .collect::<Vec<Position>>();
-        if removed.is_empty() {
-            break;
-        }
-        total_changes += removed.len();
-        for pos in removed {
-            matrix[pos] = Cell::Empty
+impl PaperGrid for DenseGrid {
+    fn remove_accessible(&mut self, stop: Stop) -> usize {
+        paper_automaton(stop, self.neighbourhood)
+            .run(&mut self.matrix)
+            .died
+    }
+}
+
+fn day04_depth_map(input: &str, neighbourhood: Neighbourhood) -> Result<Matrix<Depth>, String> {
+    let mut matrix = read_grid(input, neighbourhood)?;
+    let mut depths = matrix.map(|pos| match matrix[pos] {
+        Cell::Paper => Depth::Never,
+        Cell::Empty => Depth::Empty,
+    });
+    paper_automaton(Stop::FixedPoint, neighbourhood).run_with(&mut matrix, |round, _, removed| {
+        for &pos in removed {
+            depths[pos] = Depth::Removed(round);
         }
+    });
+    Ok(depths)
+}
+
+// Calls `on_frame` with the initial grid as round 0 and then once per removal round
+fn day04_part2_with_frames(
+    input: &str,
+    neighbourhood: Neighbourhood,
+    format: FrameFormat,
+    mut on_frame: impl FnMut(usize, String),
+) -> Result<usize, String> {
+    let mut matrix = read_grid(input, neighbourhood)?;
+    on_frame(0, frames::render(&matrix, &[], format));
+    Ok(paper_automaton(Stop::FixedPoint, neighbourhood)
+        .run_with(&mut matrix, |round, matrix, removed| {
+            on_frame(round, frames::render(matrix, removed, format))
+        })
+        .died)
+}
+
+fn paper_automaton(stop: Stop, neighbourhood: Neighbourhood) -> Automaton {
+    Automaton {
+        rule: Rule::threshold(MIN_PAPER_NEIGHBOURS),
+        update: Update::Synchronous,
+        stop,
+        neighbourhood,
     }
-    total_changes
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -71,37 +277,113 @@ enum Cell {
 
 type Position = (usize, usize);
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Shape {
+    Moore(usize),
+    VonNeumann,
+    // Rows are offset so odd rows sit half a cell to the right of even rows
+    Hexagonal,
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.split_once('=') {
+            None if input == "moore" => Ok(Shape::Moore(1)),
+            None if input == "von-neumann" => Ok(Shape::VonNeumann),
+            None if input == "hex" => Ok(Shape::Hexagonal),
+            Some(("moore", radius)) => match radius.parse() {
+                Ok(radius) if radius > 0 => Ok(Shape::Moore(radius)),
+                _ => Err(format!("invalid radius {radius:?}")),
+            },
+            _ => Err(format!("unknown neighbourhood {input:?}")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Neighbourhood {
+    shape: Shape,
+    wrap: bool,
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Self {
+        Neighbourhood {
+            shape: Shape::Moore(1),
+            wrap: false,
+        }
+    }
+}
+
+impl Neighbourhood {
+    // Wrapping hexagonal grids need an even number of rows for the offsets to line up. With an odd
+    // number the first and last rows share an offset, so neighbours are no longer mutual and the
+    // neighbour counts kept by the automaton drift.
+    fn check(&self, rows: usize) -> Result<(), String> {
+        if self.shape == Shape::Hexagonal && self.wrap && !rows.is_multiple_of(2) {
+            return Err(format!(
+                "wrapping hexagonal grids need an even number of rows, found {rows}"
+            ));
+        }
+        Ok(())
+    }
+
+    fn get_changes(&self, row: isize) -> Vec<(isize, isize)> {
+        match self.shape {
+            Shape::Moore(radius) => {
+                let radius = radius as isize;
+                (-radius..=radius)
+                    .flat_map(|i| (-radius..=radius).map(move |j| (i, j)))
+                    .filter(|&change| change != (0, 0))
+                    .collect()
+            }
+            Shape::VonNeumann => vec![(1, 0), (0, 1), (0, -1), (-1, 0)],
+            Shape::Hexagonal => {
+                let shift = row.rem_euclid(2);
+                vec![
+                    (0, -1),
+                    (0, 1),
+                    (-1, shift - 1),
+                    (-1, shift),
+                    (1, shift - 1),
+                    (1, shift),
+                ]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Matrix<T> {
     inner: Vec<Vec<T>>,
 }
 
 impl<T> Matrix<T> {
-    fn get_neighbors(&self, pos: Position) -> Vec<Position> {
-        vec![
-            (1, 1),
-            (1, 0),
-            (1, -1),
-            (0, 1),
-            (0, -1),
-            (-1, 1),
-            (-1, 0),
-            (-1, -1),
-        ]
-        .into_iter()
-        .filter(|change| {
-            (pos.0 != 0 || change.0 != -1)
-                && (pos.1 != 0 || change.1 != -1)
-                && (pos.0 != self.m() - 1 || change.0 != 1)
-                && (pos.1 != self.n() - 1 || change.1 != 1)
-        })
-        .map(|change: (isize, isize)| {
-            (
-                (pos.0 as isize + change.0) as usize,
-                (pos.1 as isize + change.1) as usize,
-            )
-        })
-        .collect()
+    fn get_neighbors(&self, pos: Position, neighbourhood: &Neighbourhood) -> Vec<Position> {
+        let (m, n) = (self.m() as isize, self.n() as isize);
+        let mut neighbours = neighbourhood
+            .get_changes(pos.0 as isize)
+            .into_iter()
+            .filter_map(|change| {
+                let (i, j) = (pos.0 as isize + change.0, pos.1 as isize + change.1);
+                if neighbourhood.wrap {
+                    Some((i.rem_euclid(m) as usize, j.rem_euclid(n) as usize))
+                } else if (0..m).contains(&i) && (0..n).contains(&j) {
+                    Some((i as usize, j as usize))
+                } else {
+                    None
+                }
+            })
+            .filter(|&neighbour| neighbour != pos)
+            .collect::<Vec<Position>>();
+        if neighbourhood.wrap {
+            // Small grids can wrap around onto the same cell more than once
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+        neighbours
     }
 
     fn m(&self) -> usize {
@@ -115,6 +397,14 @@ impl<T> Matrix<T> {
     pub fn iter(&self) -> impl Iterator<Item = Position> {
         (0..self.m()).flat_map(|i| (0..self.n()).map(move |j| (i, j)))
     }
+
+    fn map<U>(&self, mut f: impl FnMut(Position) -> U) -> Matrix<U> {
+        Matrix {
+            inner: (0..self.m())
+                .map(|i| (0..self.n()).map(|j| f((i, j))).collect())
+                .collect(),
+        }
+    }
 }
 
 impl<T> Index<Position> for Matrix<T> {
@@ -131,6 +421,12 @@ impl<T> IndexMut<Position> for Matrix<T> {
     }
 }
 
+fn read_grid(input: &str, neighbourhood: Neighbourhood) -> Result<Matrix<Cell>, String> {
+    let (_, matrix) = read_input(input).map_err(|error| error.to_string())?;
+    neighbourhood.check(matrix.m())?;
+    Ok(matrix)
+}
+
 fn read_input(input: &str) -> IResult<&str, Matrix<Cell>> {
     map(
         separated_list1(
@@ -147,32 +443,390 @@ fn read_input(input: &str) -> IResult<&str, Matrix<Cell>> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Instant;
+
     use super::*;
     #[test]
     fn part1_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day04_part1(&contents);
+        let result =
+            day04_part1(&mut DenseGrid::read(&contents, Neighbourhood::default()).unwrap());
         assert_eq!(result, 13);
     }
 
     #[test]
     fn part1_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day04_part1(&contents);
+        let result =
+            day04_part1(&mut DenseGrid::read(&contents, Neighbourhood::default()).unwrap());
         assert_eq!(result, 1518);
     }
 
     #[test]
     fn part2_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day04_part2(&contents);
+        let result =
+            day04_part2(&mut DenseGrid::read(&contents, Neighbourhood::default()).unwrap());
         assert_eq!(result, 43);
     }
 
     #[test]
     fn part2_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day04_part2(&contents);
+        let result =
+            day04_part2(&mut DenseGrid::read(&contents, Neighbourhood::default()).unwrap());
         assert_eq!(result, 2528);
     }
+
+    #[test]
+    fn asynchronous_updates_remove_the_same_paper() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let (_, mut matrix) = read_input(&contents).unwrap();
+        let automaton = Automaton {
+            rule: Rule::threshold(4),
+            update: Update::Asynchronous,
+            stop: Stop::FixedPoint,
+            neighbourhood: Neighbourhood::default(),
+        };
+        assert_eq!(automaton.run(&mut matrix).died, 43);
+    }
+
+    #[test]
+    fn life_rules_run_on_paper_grids() {
+        let (_, mut matrix) = read_input(".....\n..@..\n..@..\n..@..\n.....").unwrap();
+        let automaton = Automaton {
+            rule: "B3/S23".parse().unwrap(),
+            update: Update::Synchronous,
+            stop: Stop::Generations(1),
+            neighbourhood: Neighbourhood::default(),
+        };
+        let run = automaton.run(&mut matrix);
+        assert_eq!((run.born, run.died), (2, 2));
+        let (_, blinker) = read_input(".....\n.....\n.@@@.\n.....\n.....").unwrap();
+        assert_eq!(matrix.inner, blinker.inner);
+    }
+
+    #[test]
+    fn rules_are_read_in_birth_survival_notation() {
+        let rule: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(rule.birth, automaton::Counts::Exactly(vec![3, 6]));
+        assert_eq!(rule.survival, automaton::Counts::Exactly(vec![2, 3]));
+        assert!("S23/B3".parse::<Rule>().is_err());
+    }
+
+    fn day04_part2_rescanning(matrix: &mut Matrix<Cell>) -> usize {
+        let mut total_changes = 0;
+        loop {
+            let removed = matrix
+                .iter()
+                .filter(|&pos| {
+                    matrix[pos] == Cell::Paper
+                        && matrix
+                            .get_neighbors(pos, &Neighbourhood::default())
+                            .into_iter()
+                            .filter(|&neighbour| matrix[neighbour] == Cell::Paper)
+                            .count()
+                            < 4
+                })
+                .collect::<Vec<Position>>();
+            if removed.is_empty() {
+                break;
+            }
+            total_changes += removed.len();
+            for pos in removed {
+                matrix[pos] = Cell::Empty
+            }
+        }
+        total_changes
+    }
+
+    // Two cell wide strips only lose their two end columns each round, so they take a round
+    // per pair of columns to disappear
+    fn strips(size: usize) -> Matrix<Cell> {
+        Matrix {
+            inner: (0..size)
+                .map(|i| {
+                    let cell = if i % 4 < 2 { Cell::Paper } else { Cell::Empty };
+                    vec![cell; size]
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn incremental_removal_matches_rescanning() {
+        let automaton = Automaton {
+            rule: Rule::threshold(4),
+            update: Update::Synchronous,
+            stop: Stop::FixedPoint,
+            neighbourhood: Neighbourhood::default(),
+        };
+        let test_input = fs::read_to_string("test_input").unwrap();
+        let input = fs::read_to_string("input").unwrap();
+        for matrix in [
+            read_input(&test_input).unwrap().1,
+            read_input(&input).unwrap().1,
+            strips(40),
+        ] {
+            let mut rescanned = Matrix {
+                inner: matrix.inner.clone(),
+            };
+            let mut incremental = matrix;
+            assert_eq!(
+                automaton.run(&mut incremental).died,
+                day04_part2_rescanning(&mut rescanned)
+            );
+            assert_eq!(incremental.inner, rescanned.inner);
+        }
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn benchmark_incremental_removal() {
+        let automaton = Automaton {
+            rule: Rule::threshold(4),
+            update: Update::Synchronous,
+            stop: Stop::FixedPoint,
+            neighbourhood: Neighbourhood::default(),
+        };
+        let mut matrix = strips(600);
+        let start = Instant::now();
+        let rescanned = day04_part2_rescanning(&mut strips(600));
+        let rescanning_time = start.elapsed();
+        let start = Instant::now();
+        let incremental = automaton.run(&mut matrix).died;
+        let incremental_time = start.elapsed();
+        println!("rescanning: {rescanning_time:?}, incremental: {incremental_time:?}");
+        assert_eq!(incremental, rescanned);
+        assert!(incremental_time * 10 < rescanning_time);
+    }
+
+    #[test]
+    fn neighbourhoods_have_the_expected_cells() {
+        let matrix = strips(8);
+        let count = |shape, wrap| {
+            matrix
+                .get_neighbors((0, 0), &Neighbourhood { shape, wrap })
+                .len()
+        };
+        assert_eq!(count(Shape::Moore(1), false), 3);
+        assert_eq!(count(Shape::Moore(1), true), 8);
+        assert_eq!(count(Shape::Moore(2), false), 8);
+        assert_eq!(count(Shape::Moore(2), true), 24);
+        assert_eq!(count(Shape::VonNeumann, false), 2);
+        assert_eq!(count(Shape::VonNeumann, true), 4);
+        assert_eq!(count(Shape::Hexagonal, false), 2);
+        assert_eq!(count(Shape::Hexagonal, true), 6);
+        assert_eq!(
+            matrix.get_neighbors(
+                (3, 3),
+                &Neighbourhood {
+                    shape: Shape::Hexagonal,
+                    wrap: false
+                }
+            ),
+            vec![(3, 2), (3, 4), (2, 3), (2, 4), (4, 3), (4, 4)]
+        );
+    }
+
+    #[test]
+    fn wrapping_removes_edge_effects() {
+        // A solid torus gives every cell 8 paper neighbours
+        let contents = "@@@@\n@@@@\n@@@@\n@@@@";
+        let wrap = Neighbourhood {
+            shape: Shape::Moore(1),
+            wrap: true,
+        };
+        assert_eq!(
+            day04_part1(&mut DenseGrid::read(contents, Neighbourhood::default()).unwrap()),
+            4
+        );
+        assert_eq!(
+            day04_part1(&mut DenseGrid::read(contents, wrap).unwrap()),
+            0
+        );
+        let von_neumann = Neighbourhood {
+            shape: Shape::VonNeumann,
+            wrap: false,
+        };
+        assert_eq!(
+            day04_part2(&mut DenseGrid::read(contents, von_neumann).unwrap()),
+            16
+        );
+    }
+
+    #[test]
+    fn shapes_are_read_from_arguments() {
+        assert_eq!("moore".parse(), Ok(Shape::Moore(1)));
+        assert_eq!("moore=3".parse(), Ok(Shape::Moore(3)));
+        assert_eq!("von-neumann".parse(), Ok(Shape::VonNeumann));
+        assert_eq!("hex".parse(), Ok(Shape::Hexagonal));
+        assert!("moore=0".parse::<Shape>().is_err());
+        let args = ["--neighbourhood", "hexx", "--generations", "x"].map(String::from);
+        assert_eq!(
+            parse_option::<Shape>(&args, "--neighbourhood"),
+            Err("--neighbourhood: unknown neighbourhood \"hexx\"".to_string())
+        );
+        assert!(parse_option::<usize>(&args, "--generations").is_err());
+        assert_eq!(
+            parse_option::<FrameFormat>(&args, "--frame-format"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn depth_map_records_removal_rounds() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let depths = day04_depth_map(&contents, Neighbourhood::default()).unwrap();
+        let removed = depths
+            .iter()
+            .filter(|&pos| matches!(depths[pos], Depth::Removed(_)))
+            .count();
+        assert_eq!(removed, 43);
+        assert_eq!(depths[(0, 0)], Depth::Empty);
+        assert_eq!(depths[(0, 2)], Depth::Removed(1));
+        assert_eq!(depths[(4, 4)], Depth::Never);
+        let text = depths.to_text();
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.starts_with(". . 1 1 . 1 1 2 1 .\n1 3 4 . 2 . 2 . 3 2\n"));
+        assert!(depths.to_pgm().starts_with("P2\n10 10\n255\n0 0 1 1 0"));
+        assert!(depths.to_ppm().starts_with("P3\n10 10\n255\n255 255 255"));
+    }
+
+    #[test]
+    fn bit_grid_matches_dense_grid() {
+        let test_input = fs::read_to_string("test_input").unwrap();
+        let input = fs::read_to_string("input").unwrap();
+        // Wide enough to cross several words per row
+        let wide = (0..12)
+            .map(|i| {
+                if i % 4 < 2 {
+                    "@".repeat(200)
+                } else {
+                    ".".repeat(200)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        for contents in [&test_input, &input, &wide] {
+            let mut bits = BitGrid::read(contents.as_bytes()).unwrap();
+            let mut dense = DenseGrid::read(contents, Neighbourhood::default()).unwrap();
+            assert_eq!(day04_part1(&mut bits), day04_part1(&mut dense));
+            let mut bits = BitGrid::read(contents.as_bytes()).unwrap();
+            let mut dense = DenseGrid::read(contents, Neighbourhood::default()).unwrap();
+            assert_eq!(day04_part2(&mut bits), day04_part2(&mut dense));
+        }
+    }
+
+    #[test]
+    fn bit_grid_rejects_invalid_drawings() {
+        assert!(BitGrid::read("@.@\n@.".as_bytes()).is_err());
+        assert!(BitGrid::read("@.@\n@x@".as_bytes()).is_err());
+        let hex = Neighbourhood {
+            shape: Shape::Hexagonal,
+            wrap: false,
+        };
+        assert!(read_bit_grid("@.@".as_bytes(), hex).is_err());
+        assert!(read_bit_grid("@.@".as_bytes(), Neighbourhood::default()).is_ok());
+        let args = ["--backend", "bits", "--depth-map", "depths.pgm"].map(String::from);
+        assert_eq!(
+            check_bits_options(&args),
+            Err("the bits backend doesn't support --depth-map".to_string())
+        );
+        assert_eq!(check_bits_options(&args[..2]), Ok(()));
+    }
+
+    #[test]
+    fn frames_highlight_removed_paper() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let mut frames = Vec::new();
+        let removed = day04_part2_with_frames(
+            &contents,
+            Neighbourhood::default(),
+            FrameFormat::Ascii,
+            |round, frame| frames.push((round, frame)),
+        );
+        assert_eq!(removed, Ok(43));
+        assert_eq!(frames.len(), 10);
+        assert_eq!(frames[0].1, contents.trim_end().to_string() + "\n");
+        assert!(frames[1].1.starts_with("..xx.xx@x.\nx@@.@.@.@@\n"));
+        let highlighted: usize = frames
+            .iter()
+            .map(|(_, frame)| frame.matches('x').count())
+            .sum();
+        assert_eq!(highlighted, 43);
+
+        let mut frames = Vec::new();
+        day04_part2_with_frames(
+            &contents,
+            Neighbourhood::default(),
+            FrameFormat::Ppm,
+            |round, frame| frames.push((round, frame)),
+        )
+        .unwrap();
+        assert!(
+            frames[1]
+                .1
+                .starts_with("P3\n10 10\n255\n255 255 255 255 255 255 220 30 30")
+        );
+    }
+
+    #[test]
+    fn sparse_grid_matches_dense_grid() {
+        let test_input = fs::read_to_string("test_input").unwrap();
+        let input = fs::read_to_string("input").unwrap();
+        for contents in [&test_input, &input] {
+            for shape in [
+                Shape::Moore(1),
+                Shape::Moore(2),
+                Shape::VonNeumann,
+                Shape::Hexagonal,
+            ] {
+                let neighbourhood = Neighbourhood { shape, wrap: false };
+                let sparse = SparseGrid::read_drawing(contents, neighbourhood).unwrap();
+                let mut dense = DenseGrid::read(contents, neighbourhood).unwrap();
+                assert_eq!(day04_part1(&mut sparse.clone()), day04_part1(&mut dense));
+                let mut dense = DenseGrid::read(contents, neighbourhood).unwrap();
+                assert_eq!(day04_part2(&mut sparse.clone()), day04_part2(&mut dense));
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_grid_reads_signed_coordinates() {
+        // A 3x3 block far away from a 2x2 block on the other side of the origin
+        let coordinates = (-1_000_001..=-999_999)
+            .flat_map(|x| (4..=6).map(move |y| format!("{x},{y}")))
+            .chain(["7,-3", "8,-3", "7,-2", "8,-2"].map(String::from))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let grid = SparseGrid::read_coordinates(&coordinates, Neighbourhood::default()).unwrap();
+        assert_eq!(day04_part1(&mut grid.clone()), 8);
+        assert_eq!(day04_part2(&mut grid.clone()), 13);
+        let wrap = Neighbourhood {
+            shape: Shape::Moore(1),
+            wrap: true,
+        };
+        assert!(SparseGrid::read_coordinates("1,2", wrap).is_err());
+        assert!(SparseGrid::read_coordinates("1;2", Neighbourhood::default()).is_err());
+        assert!(SparseGrid::read_coordinates("1,2\n3", Neighbourhood::default()).is_err());
+    }
+
+    #[test]
+    fn wrapping_hexagonal_grids_need_even_rows() {
+        let hex_wrap = Neighbourhood {
+            shape: Shape::Hexagonal,
+            wrap: true,
+        };
+        assert!(DenseGrid::read("@@@\n@@@\n@@@", hex_wrap).is_err());
+        assert!(day04_depth_map("@@@\n@@@\n@@@", hex_wrap).is_err());
+        let mut grid = DenseGrid::read("@@@\n@@@\n@@@\n@@@", hex_wrap).unwrap();
+        assert_eq!(day04_part2(&mut grid), 0);
+        let hex = Neighbourhood {
+            shape: Shape::Hexagonal,
+            wrap: false,
+        };
+        assert!(DenseGrid::read("@@@\n@@@\n@@@", hex).is_ok());
+    }
 }
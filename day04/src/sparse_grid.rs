@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use nom::{
+    IResult, Parser, bytes::tag, character::complete::multispace1, multi::separated_list1,
+    sequence::separated_pair,
+};
+
+use crate::{MIN_PAPER_NEIGHBOURS, Neighbourhood, PaperGrid, automaton::Stop};
+
+// Row and column, which can be anywhere
+type SparsePosition = (i64, i64);
+
+// Only the positions with paper are stored, so the grid has no bounds
+#[derive(Debug, Clone)]
+pub struct SparseGrid {
+    paper: HashSet<SparsePosition>,
+    neighbourhood: Neighbourhood,
+}
+
+impl SparseGrid {
+    fn new(
+        paper: HashSet<SparsePosition>,
+        neighbourhood: Neighbourhood,
+    ) -> Result<SparseGrid, String> {
+        if neighbourhood.wrap {
+            return Err("unbounded grids can't wrap around".to_string());
+        }
+        Ok(SparseGrid {
+            paper,
+            neighbourhood,
+        })
+    }
+
+    pub fn read_drawing(input: &str, neighbourhood: Neighbourhood) -> Result<SparseGrid, String> {
+        let mut paper = HashSet::new();
+        for (i, line) in input.lines().enumerate() {
+            for (j, c) in line.trim_end().chars().enumerate() {
+                match c {
+                    '@' => {
+                        paper.insert((i as i64, j as i64));
+                    }
+                    '.' => {}
+                    _ => return Err(format!("invalid cell {c:?} at row {}", i + 1)),
+                }
+            }
+        }
+        SparseGrid::new(paper, neighbourhood)
+    }
+
+    // One `x,y` pair per line, where `x` is the column and `y` the row
+    pub fn read_coordinates(
+        input: &str,
+        neighbourhood: Neighbourhood,
+    ) -> Result<SparseGrid, String> {
+        let (remaining, coordinates) = read_coordinates(input.trim())
+            .map_err(|error| format!("invalid coordinates: {error}"))?;
+        if !remaining.is_empty() {
+            return Err(format!("invalid coordinates at {remaining:?}"));
+        }
+        let paper = coordinates.into_iter().map(|(x, y)| (y, x)).collect();
+        SparseGrid::new(paper, neighbourhood)
+    }
+
+    fn get_neighbors(&self, pos: SparsePosition) -> impl Iterator<Item = SparsePosition> {
+        self.neighbourhood
+            .get_changes(pos.0 as isize)
+            .into_iter()
+            .map(move |change| (pos.0 + change.0 as i64, pos.1 + change.1 as i64))
+    }
+
+    fn is_accessible(&self, pos: SparsePosition) -> bool {
+        self.paper.contains(&pos)
+            && self
+                .get_neighbors(pos)
+                .filter(|neighbour| self.paper.contains(neighbour))
+                .count()
+                < MIN_PAPER_NEIGHBOURS
+    }
+}
+
+impl PaperGrid for SparseGrid {
+    fn remove_accessible(&mut self, stop: Stop) -> usize {
+        let mut removed = 0;
+        let mut rounds = 0;
+        let mut candidates = self.paper.clone();
+        while stop != Stop::Generations(rounds) {
+            let accessible = candidates
+                .iter()
+                .copied()
+                .filter(|&pos| self.is_accessible(pos))
+                .collect::<Vec<SparsePosition>>();
+            if accessible.is_empty() {
+                break;
+            }
+            for pos in &accessible {
+                self.paper.remove(pos);
+            }
+            // Only paper next to what was just removed can become accessible
+            candidates = accessible
+                .iter()
+                .flat_map(|&pos| self.get_neighbors(pos))
+                .filter(|neighbour| self.paper.contains(neighbour))
+                .collect();
+            removed += accessible.len();
+            rounds += 1;
+        }
+        removed
+    }
+}
+
+fn read_coordinates(input: &str) -> IResult<&str, Vec<(i64, i64)>> {
+    separated_list1(
+        multispace1,
+        separated_pair(
+            nom::character::complete::i64,
+            tag(","),
+            nom::character::complete::i64,
+        ),
+    )
+    .parse_complete(input)
+}
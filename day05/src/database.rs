@@ -0,0 +1,86 @@
+use std::ops::RangeInclusive;
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::tag,
+    character::complete::space1,
+    combinator::{map, value},
+    sequence::preceded,
+};
+
+use crate::{
+    range_set::{Bound, RangeSet},
+    read_number, read_range,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command<T = u64> {
+    Add(RangeInclusive<T>),
+    Remove(RangeInclusive<T>),
+    Query(T),
+    Coverage,
+}
+
+// Fresh ranges that change over time, answering queries as the commands arrive
+#[derive(Debug)]
+pub struct RangeDatabase<T = u64> {
+    fresh: RangeSet<T>,
+}
+
+impl<T> Default for RangeDatabase<T> {
+    fn default() -> Self {
+        RangeDatabase {
+            fresh: RangeSet::default(),
+        }
+    }
+}
+
+impl<T: Bound> RangeDatabase<T> {
+    pub fn execute(&mut self, command: Command<T>) -> String {
+        match command {
+            Command::Add(range) => {
+                self.fresh.insert(range);
+                "ok".to_string()
+            }
+            Command::Remove(range) => {
+                self.fresh.remove(range);
+                "ok".to_string()
+            }
+            Command::Query(ingredient) => {
+                if self.fresh.contains(ingredient) {
+                    "fresh".to_string()
+                } else {
+                    "rotten".to_string()
+                }
+            }
+            Command::Coverage => match self.fresh.len() {
+                Some(len) => len.to_string(),
+                None => "error: coverage doesn't fit in 128 bits".to_string(),
+            },
+        }
+    }
+
+    pub fn execute_line(&mut self, line: &str) -> String {
+        match read_command(line.trim()) {
+            Ok(("", command)) => self.execute(command),
+            _ => format!("error: invalid command {:?}", line.trim()),
+        }
+    }
+}
+
+fn read_command<T: Bound>(input: &str) -> IResult<&str, Command<T>> {
+    alt((
+        map(preceded((tag("add"), space1), read_range), Command::Add),
+        map(
+            preceded((tag("remove"), space1), read_range),
+            Command::Remove,
+        ),
+        map(
+            preceded((tag("query"), space1), read_number),
+            Command::Query,
+        ),
+        value(Command::Coverage, tag("coverage")),
+    ))
+    .parse_complete(input)
+}
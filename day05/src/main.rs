@@ -1,69 +1,112 @@
-use std::{cmp::max, collections::HashSet, fs, ops::RangeInclusive};
+mod database;
+// The set operations are part of the type but only the tests use them so far
+#[allow(unused)]
+mod range_set;
+mod report;
+
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    ops::RangeInclusive,
+};
+
+use database::RangeDatabase;
+use range_set::{Bound, RangeSet};
+use report::MatchReport;
 
 use nom::{
     IResult, Parser,
     bytes::tag,
     character::complete::{digit1, multispace1},
-    combinator::map,
+    combinator::{map, map_res, opt, recognize},
     multi::separated_list1,
-    sequence::separated_pair,
+    sequence::{preceded, separated_pair},
 };
 
 fn main() {
     let contents = fs::read_to_string("input").unwrap();
-    let result = day05_part1(&contents);
-    println!("Day05 part 1 result: {result}");
-    let result = day05_part2(&contents);
-    println!("Day05 part 2 result: {result}");
+    let args = env::args().collect::<Vec<String>>();
+    match args
+        .iter()
+        .position(|arg| arg == "--type")
+        .and_then(|index| args.get(index + 1))
+        .map(|bound| bound.as_str())
+    {
+        Some("i64") => run::<i64>(&contents, &args),
+        Some("u128") => run::<u128>(&contents, &args),
+        Some("i128") => run::<i128>(&contents, &args),
+        _ => run::<u64>(&contents, &args),
+    }
 }
 
-fn day05_part1(input: &str) -> usize {
-    let (_, (fresh_ranges, ingredients)) = read_input(input).unwrap();
-    let mut rotten_ingredients = ingredients.iter().collect::<HashSet<_>>();
-    for range in fresh_ranges {
-        rotten_ingredients.retain(|ingredient| !range.contains(ingredient));
+fn run<T: Bound>(contents: &str, args: &[String]) {
+    if args.iter().any(|arg| arg == "--serve") {
+        // Answers one command per line from stdin until it is closed
+        let mut database = RangeDatabase::<T>::default();
+        let mut stdout = io::stdout().lock();
+        for line in io::stdin().lock().lines() {
+            writeln!(stdout, "{}", database.execute_line(&line.unwrap())).unwrap();
+            stdout.flush().unwrap();
+        }
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--report") {
+        let report = day05_report::<T>(contents);
+        match args.get(index + 1).map(|format| format.as_str()) {
+            Some("json") => println!("{}", report.to_json()),
+            _ => print!("{}", report.to_table()),
+        }
+        return;
+    }
+    let result = day05_part1::<T>(contents);
+    println!("Day05 part 1 result: {result}");
+    match day05_part2::<T>(contents) {
+        Some(result) => println!("Day05 part 2 result: {result}"),
+        None => println!("Day05 error: coverage doesn't fit in 128 bits"),
     }
-    ingredients.len() - rotten_ingredients.len()
 }
 
-fn day05_part2(input: &str) -> usize {
-    let (_, (mut fresh_ranges, _)) = read_input(input).unwrap();
-    fresh_ranges.sort_by_key(|range| *range.start());
-    fresh_ranges
-        .iter()
-        .fold((0, 0), |(total, index), range| {
-            let end = *range.end();
-            let start = *range.start();
-            let new_total = if index >= end {
-                total
-            } else if index < start {
-                total + end - start + 1
-            } else {
-                total + end - index
-            };
-            let new_index = max(index, *range.end());
-            (new_total, new_index)
-        })
-        .0 as usize
+fn day05_part1<T: Bound>(input: &str) -> usize {
+    let (_, (fresh_ranges, ingredients)) = read_input::<T>(input).unwrap();
+    let fresh = RangeSet::from_iter(fresh_ranges);
+    // Every listed ingredient counts, duplicates included, and is looked up with a binary search
+    // over the merged ranges
+    ingredients
+        .into_iter()
+        .filter(|&ingredient| fresh.contains(ingredient))
+        .count()
+}
+
+fn day05_part2<T: Bound>(input: &str) -> Option<u128> {
+    let (_, (fresh_ranges, _)) = read_input::<T>(input).unwrap();
+    RangeSet::from_iter(fresh_ranges).len()
 }
 
-fn read_input(input: &str) -> IResult<&str, (Vec<RangeInclusive<u64>>, Vec<u64>)> {
+fn day05_report<T: Bound>(input: &str) -> MatchReport<T> {
+    let (_, (fresh_ranges, ingredients)) = read_input::<T>(input).unwrap();
+    MatchReport::new(fresh_ranges, &ingredients)
+}
+
+fn read_input<T: Bound>(input: &str) -> IResult<&str, (Vec<RangeInclusive<T>>, Vec<T>)> {
     separated_pair(
-        separated_list1(
-            multispace1,
-            map(
-                separated_pair(read_number, tag("-"), read_number),
-                |(a, b)| a..=b,
-            ),
-        ),
+        separated_list1(multispace1, read_range),
         multispace1,
         separated_list1(multispace1, read_number),
     )
     .parse_complete(input)
 }
 
-fn read_number(input: &str) -> IResult<&str, u64> {
-    map(digit1, |s: &str| s.parse().unwrap()).parse_complete(input)
+// Negative bounds keep their sign, so `-5--2` goes from -5 to -2
+fn read_range<T: Bound>(input: &str) -> IResult<&str, RangeInclusive<T>> {
+    map(
+        separated_pair(read_number, tag("-"), read_number),
+        |(a, b)| a..=b,
+    )
+    .parse_complete(input)
+}
+
+fn read_number<T: Bound>(input: &str) -> IResult<&str, T> {
+    map_res(recognize(preceded(opt(tag("-")), digit1)), str::parse).parse_complete(input)
 }
 
 #[cfg(test)]
@@ -72,28 +115,180 @@ mod tests {
     #[test]
     fn part1_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day05_part1(&contents);
+        let result = day05_part1::<u64>(&contents);
         assert_eq!(result, 3);
     }
 
     #[test]
     fn part1_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day05_part1(&contents);
+        let result = day05_part1::<u64>(&contents);
         assert_eq!(result, 638);
     }
 
     #[test]
     fn part2_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day05_part2(&contents);
-        assert_eq!(result, 14);
+        let result = day05_part2::<u64>(&contents);
+        assert_eq!(result, Some(14));
     }
 
     #[test]
     fn part2_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day05_part2(&contents);
-        assert_eq!(result, 352946349407338);
+        let result = day05_part2::<u64>(&contents);
+        assert_eq!(result, Some(352946349407338));
+    }
+
+    #[test]
+    fn part1_counts_duplicate_ingredients() {
+        let result = day05_part1::<u64>("3-5\n10-14\n\n4\n4\n4\n1\n1\n12");
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn part1_counts_ingredients_on_range_boundaries() {
+        let result = day05_part1::<u64>("3-5\n10-14\n\n2\n3\n5\n6\n9\n10\n14\n15");
+        assert_eq!(result, 4);
+        let result = day05_part1::<u64>(
+            "0-0\n18446744073709551615-18446744073709551615\n\n0\n1\n18446744073709551615",
+        );
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn report_lists_matching_ranges() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let report = day05_report::<u64>(&contents);
+        let matches = report
+            .ingredients
+            .iter()
+            .map(|ingredient_match| (ingredient_match.ingredient, ingredient_match.ranges.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            matches,
+            vec![
+                (1, vec![]),
+                (5, vec![0]),
+                (8, vec![]),
+                (11, vec![1]),
+                (17, vec![2, 3]),
+                (32, vec![])
+            ]
+        );
+        assert_eq!(report.hits, vec![1, 1, 1, 1]);
+        assert_eq!(report.unmatched_ranges().count(), 0);
+        assert!(report.to_table().contains("17\t16-20, 12-18\n"));
+        assert!(report.to_table().ends_with("unmatched ranges: none\n"));
+    }
+
+    #[test]
+    fn report_finds_unmatched_ranges() {
+        let report = day05_report::<u64>("3-5\n10-14\n\n4\n4");
+        assert_eq!(report.hits, vec![2, 0]);
+        assert_eq!(
+            report.unmatched_ranges().collect::<Vec<_>>(),
+            vec![&(10..=14)]
+        );
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                r#"{"ingredients":[{"id":4,"fresh":true,"ranges":[{"start":3,"end":5}]},"#,
+                r#"{"id":4,"fresh":true,"ranges":[{"start":3,"end":5}]}],"#,
+                r#""ranges":[{"start":3,"end":5,"hits":2},{"start":10,"end":14,"hits":0}],"#,
+                r#""unmatched":[{"start":10,"end":14}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn report_and_database_accept_signed_bounds() {
+        let report = day05_report::<i64>("-5--2\n1-3\n\n-3\n0");
+        assert_eq!(report.hits, vec![1, 0]);
+        assert!(report.to_table().contains("-3\t-5--2\n"));
+        let mut database = RangeDatabase::<i64>::default();
+        let responses = ["add -5--2", "query -3", "query 0", "coverage"]
+            .map(|line| database.execute_line(line));
+        assert_eq!(responses, ["ok", "fresh", "rotten", "4"]);
+    }
+
+    #[test]
+    fn database_answers_streamed_commands() {
+        let mut database = RangeDatabase::<u64>::default();
+        let responses = [
+            "add 3-5",
+            "add 10-14",
+            "query 4",
+            "remove 4-4",
+            "query 4",
+            "query 5",
+            "coverage",
+            "add 6-9",
+            "coverage",
+            "query 7",
+            "frobnicate 7",
+            "add 3-",
+        ]
+        .map(|line| database.execute_line(line));
+        assert_eq!(
+            responses,
+            [
+                "ok",
+                "ok",
+                "fresh",
+                "ok",
+                "rotten",
+                "fresh",
+                "7",
+                "ok",
+                "11",
+                "fresh",
+                "error: invalid command \"frobnicate 7\"",
+                "error: invalid command \"add 3-\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn database_matches_part2_coverage() {
+        let contents = fs::read_to_string("input").unwrap();
+        let (_, (fresh_ranges, _)) = read_input::<u64>(&contents).unwrap();
+        let mut database = RangeDatabase::<u64>::default();
+        for range in fresh_ranges {
+            database.execute(database::Command::Add(range));
+        }
+        assert_eq!(
+            database.execute_line("coverage"),
+            day05_part2::<u64>(&contents).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn signed_ranges_are_read_unambiguously() {
+        let contents = "-5--2\n-3-1\n10-12\n\n-6\n-5\n0\n2\n11";
+        assert_eq!(day05_part1::<i64>(contents), 3);
+        assert_eq!(day05_part2::<i64>(contents), Some(10));
+        assert_eq!(day05_part1::<i128>(contents), 3);
+        assert!(read_input::<u64>(contents).is_err());
+    }
+
+    #[test]
+    fn coverage_can_exceed_u64() {
+        let contents = "0-18446744073709551615\n\n0";
+        assert_eq!(day05_part2::<u64>(contents), Some(1 << 64));
+        let contents = "0-18446744073709551615\n18446744073709551616-36893488147419103231\n\n0";
+        assert_eq!(day05_part2::<u128>(contents), Some(1 << 65));
+        let contents = "-9223372036854775808-9223372036854775807\n\n0";
+        assert_eq!(day05_part2::<i64>(contents), Some(1 << 64));
+        let contents =
+            "-170141183460469231731687303715884105728-170141183460469231731687303715884105727\n\n0";
+        assert_eq!(day05_part2::<i128>(contents), None);
+        let mut database = RangeDatabase::<i128>::default();
+        let responses = [
+            "add -170141183460469231731687303715884105728-170141183460469231731687303715884105727",
+            "coverage",
+        ]
+        .map(|line| database.execute_line(line));
+        assert_eq!(responses, ["ok", "error: coverage doesn't fit in 128 bits"]);
     }
 }
@@ -0,0 +1,230 @@
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+// Integer types that can be stored in a `RangeSet`
+pub trait Bound: Copy + Ord + Display + FromStr {
+    fn predecessor(self) -> Option<Self>;
+    fn successor(self) -> Option<Self>;
+    // Amount of integers in `start..=end` minus one, which always fits in a u128
+    fn distance(start: Self, end: Self) -> u128;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(
+            impl Bound for $t {
+                fn predecessor(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn distance(start: Self, end: Self) -> u128 {
+                    end.abs_diff(start) as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_bound!(u64, i64, u128, i128);
+
+// Set of integers stored as sorted, disjoint and non adjacent inclusive ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeSet<T = u64> {
+    spans: Vec<RangeInclusive<T>>,
+}
+
+impl<T> Default for RangeSet<T> {
+    fn default() -> Self {
+        RangeSet { spans: Vec::new() }
+    }
+}
+
+impl<T: Bound> RangeSet<T> {
+    pub fn new() -> Self {
+        RangeSet::default()
+    }
+
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        let (start, end) = (*range.start(), *range.end());
+        if start > end {
+            return;
+        }
+        // Spans that overlap or touch the new range are merged into it
+        let first = self
+            .spans
+            .partition_point(|span| *span.end() < start.predecessor().unwrap_or(start));
+        let last = self
+            .spans
+            .partition_point(|span| *span.start() <= end.successor().unwrap_or(end));
+        if first == last {
+            self.spans.insert(first, range);
+            return;
+        }
+        let merged = start.min(*self.spans[first].start())..=end.max(*self.spans[last - 1].end());
+        self.spans.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, range: RangeInclusive<T>) {
+        let (start, end) = (*range.start(), *range.end());
+        if start > end {
+            return;
+        }
+        let first = self.spans.partition_point(|span| *span.end() < start);
+        let last = self.spans.partition_point(|span| *span.start() <= end);
+        if first == last {
+            return;
+        }
+        let mut leftovers = Vec::with_capacity(2);
+        if *self.spans[first].start() < start {
+            leftovers.push(*self.spans[first].start()..=start.predecessor().unwrap());
+        }
+        if *self.spans[last - 1].end() > end {
+            leftovers.push(end.successor().unwrap()..=*self.spans[last - 1].end());
+        }
+        self.spans.splice(first..last, leftovers);
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.spans.partition_point(|span| *span.end() < value);
+        self.spans
+            .get(index)
+            .is_some_and(|span| *span.start() <= value)
+    }
+
+    pub fn union(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut union = self.clone();
+        for span in other.iter() {
+            union.insert(span.clone());
+        }
+        union
+    }
+
+    pub fn difference(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut difference = self.clone();
+        for span in other.iter() {
+            difference.remove(span.clone());
+        }
+        difference
+    }
+
+    pub fn intersection(&self, other: &RangeSet<T>) -> RangeSet<T> {
+        let mut intersection = RangeSet::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.spans.len() && j < other.spans.len() {
+            let (a, b) = (&self.spans[i], &other.spans[j]);
+            let start = *a.start().max(b.start());
+            let end = *a.end().min(b.end());
+            if start <= end {
+                intersection.spans.push(start..=end);
+            }
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        intersection
+    }
+
+    // Amount of integers in the set, or None when it doesn't fit in a u128, which only happens
+    // for a set covering every 128 bit integer
+    pub fn len(&self) -> Option<u128> {
+        self.spans.iter().try_fold(0, |total: u128, span| {
+            total
+                .checked_add(T::distance(*span.start(), *span.end()))?
+                .checked_add(1)
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RangeInclusive<T>> {
+        self.spans.iter()
+    }
+}
+
+impl<T: Bound> FromIterator<RangeInclusive<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans<T: Bound>(set: &RangeSet<T>) -> Vec<RangeInclusive<T>> {
+        set.iter().cloned().collect()
+    }
+
+    #[test]
+    fn insert_coalesces_overlapping_and_adjacent_ranges() {
+        let mut set = RangeSet::<u64>::from_iter([10..=14, 3..=5, 16..=20, 12..=18]);
+        assert_eq!(spans(&set), vec![3..=5, 10..=20]);
+        set.insert(6..=9);
+        assert_eq!(spans(&set), vec![3..=20]);
+        set.insert(0..=0);
+        set.insert(u64::MAX - 1..=u64::MAX);
+        assert_eq!(spans(&set), vec![0..=0, 3..=20, u64::MAX - 1..=u64::MAX]);
+        #[allow(clippy::reversed_empty_ranges)]
+        set.insert(8..=2);
+        assert_eq!(set.len(), Some(1 + 18 + 2));
+    }
+
+    #[test]
+    fn remove_splits_ranges() {
+        let mut set = RangeSet::<u64>::from_iter([3..=20, 30..=40]);
+        set.remove(5..=5);
+        assert_eq!(spans(&set), vec![3..=4, 6..=20, 30..=40]);
+        set.remove(10..=35);
+        assert_eq!(spans(&set), vec![3..=4, 6..=9, 36..=40]);
+        set.remove(0..=100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn contains_finds_boundaries() {
+        let set = RangeSet::<u64>::from_iter([3..=5, 10..=14]);
+        let contained = (0..16)
+            .filter(|&value| set.contains(value))
+            .collect::<Vec<_>>();
+        assert_eq!(contained, vec![3, 4, 5, 10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = RangeSet::<u64>::from_iter([0..=10, 20..=30]);
+        let b = RangeSet::<u64>::from_iter([5..=25, 40..=50]);
+        assert_eq!(spans(&a.union(&b)), vec![0..=30, 40..=50]);
+        assert_eq!(spans(&a.intersection(&b)), vec![5..=10, 20..=25]);
+        assert_eq!(spans(&a.difference(&b)), vec![0..=4, 26..=30]);
+        assert_eq!(spans(&b.difference(&a)), vec![11..=19, 40..=50]);
+    }
+
+    #[test]
+    fn signed_and_wide_bounds() {
+        let mut set = RangeSet::from_iter([-5..=-2, -1..=3, 10..=i64::MAX]);
+        assert_eq!(spans(&set), vec![-5..=3, 10..=i64::MAX]);
+        set.remove(i64::MIN..=-4);
+        assert_eq!(spans(&set), vec![-3..=3, 10..=i64::MAX]);
+        assert!(set.contains(-3) && !set.contains(-4));
+
+        let full = RangeSet::from_iter([0..=u64::MAX]);
+        assert_eq!(full.len(), Some(1 << 64));
+        let full = RangeSet::from_iter([i64::MIN..=i64::MAX]);
+        assert_eq!(full.len(), Some(1 << 64));
+        let wide = RangeSet::from_iter([0..=u128::MAX - 1]);
+        assert_eq!(wide.len(), Some(u128::MAX));
+        let full = RangeSet::from_iter([i128::MIN..=i128::MAX]);
+        assert_eq!(full.len(), None);
+    }
+}
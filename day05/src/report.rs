@@ -0,0 +1,134 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use crate::range_set::Bound;
+
+#[derive(Debug, PartialEq)]
+pub struct IngredientMatch<T = u64> {
+    pub ingredient: T,
+    // Indices of the fresh ranges containing the ingredient
+    pub ranges: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchReport<T = u64> {
+    pub fresh_ranges: Vec<RangeInclusive<T>>,
+    pub ingredients: Vec<IngredientMatch<T>>,
+    // Number of listed ingredients inside each fresh range
+    pub hits: Vec<usize>,
+}
+
+impl<T: Bound> MatchReport<T> {
+    pub fn new(fresh_ranges: Vec<RangeInclusive<T>>, ingredients: &[T]) -> Self {
+        let mut hits = vec![0; fresh_ranges.len()];
+        let ingredients = ingredients
+            .iter()
+            .map(|&ingredient| {
+                let ranges = fresh_ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, range)| range.contains(&ingredient))
+                    .map(|(index, _)| index)
+                    .collect::<Vec<usize>>();
+                for &index in &ranges {
+                    hits[index] += 1;
+                }
+                IngredientMatch { ingredient, ranges }
+            })
+            .collect();
+        MatchReport {
+            fresh_ranges,
+            ingredients,
+            hits,
+        }
+    }
+
+    pub fn unmatched_ranges(&self) -> impl Iterator<Item = &RangeInclusive<T>> {
+        self.fresh_ranges
+            .iter()
+            .zip(&self.hits)
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(range, _)| range)
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut table = String::from("ingredient\tfresh ranges\n");
+        for ingredient_match in &self.ingredients {
+            let ranges = if ingredient_match.ranges.is_empty() {
+                "none".to_string()
+            } else {
+                ingredient_match
+                    .ranges
+                    .iter()
+                    .map(|&index| format_range(&self.fresh_ranges[index]))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            table.push_str(&format!("{}\t{ranges}\n", ingredient_match.ingredient));
+        }
+        table.push_str("\nrange\thits\n");
+        for (range, hits) in self.fresh_ranges.iter().zip(&self.hits) {
+            table.push_str(&format!("{}\t{hits}\n", format_range(range)));
+        }
+        let unmatched = self
+            .unmatched_ranges()
+            .map(format_range)
+            .collect::<Vec<String>>();
+        let unmatched = if unmatched.is_empty() {
+            "none".to_string()
+        } else {
+            unmatched.join(", ")
+        };
+        table.push_str(&format!("\nunmatched ranges: {unmatched}\n"));
+        table
+    }
+
+    pub fn to_json(&self) -> String {
+        let ingredients = self
+            .ingredients
+            .iter()
+            .map(|ingredient_match| {
+                let ranges = ingredient_match
+                    .ranges
+                    .iter()
+                    .map(|&index| range_to_json(&self.fresh_ranges[index]))
+                    .collect::<Vec<String>>();
+                format!(
+                    "{{\"id\":{},\"fresh\":{},\"ranges\":[{}]}}",
+                    ingredient_match.ingredient,
+                    !ingredient_match.ranges.is_empty(),
+                    ranges.join(",")
+                )
+            })
+            .collect::<Vec<String>>();
+        let ranges = self
+            .fresh_ranges
+            .iter()
+            .zip(&self.hits)
+            .map(|(range, hits)| {
+                format!(
+                    "{{\"start\":{},\"end\":{},\"hits\":{hits}}}",
+                    range.start(),
+                    range.end()
+                )
+            })
+            .collect::<Vec<String>>();
+        let unmatched = self
+            .unmatched_ranges()
+            .map(range_to_json)
+            .collect::<Vec<String>>();
+        format!(
+            "{{\"ingredients\":[{}],\"ranges\":[{}],\"unmatched\":[{}]}}",
+            ingredients.join(","),
+            ranges.join(","),
+            unmatched.join(",")
+        )
+    }
+}
+
+fn format_range<T: Display>(range: &RangeInclusive<T>) -> String {
+    format!("{}-{}", range.start(), range.end())
+}
+
+fn range_to_json<T: Display>(range: &RangeInclusive<T>) -> String {
+    format!("{{\"start\":{},\"end\":{}}}", range.start(), range.end())
+}
@@ -1,120 +1,454 @@
-use std::fs;
-
-use nom::{
-    IResult, Parser,
-    branch::alt,
-    bytes::tag,
-    character::{
-        complete::{digit1, line_ending, multispace1, space1},
-        satisfy,
-    },
-    combinator::map,
-    multi::{many1, separated_list1},
-    sequence::separated_pair,
+use std::{
+    env,
+    fmt::{self, Display},
+    fs,
+    ops::Range,
+    str::FromStr,
 };
 
+use nom::{IResult, Parser, branch::alt, bytes::tag, combinator::map};
+
 fn main() {
     let contents = fs::read_to_string("input").unwrap();
-    let result = day06_part1(&contents);
-    println!("Day06 part 1 result: {result}");
-    let result = day06_part2(&contents);
-    println!("Day06 part 2 result: {result}");
-}
-
-fn day06_part1(input: &str) -> u64 {
-    let (_, (numbers, operations)) = read_input(input).unwrap();
-    numbers
-        .into_iter()
-        .reduce(|results, new_operands| {
-            results
-                .iter()
-                .zip(new_operands)
-                .zip(operations.iter())
-                .map(|((a, b), op)| match op {
-                    Operation::Sum => a + b,
-                    Operation::Multiplication => a * b,
-                })
-                .collect::<Vec<u64>>()
-        })
-        .unwrap()
-        .into_iter()
-        .sum()
-}
-fn day06_part2(input: &str) -> u64 {
-    let (_, (numbers, operations)) = read_input_part2(input).unwrap();
-    numbers
-        .into_iter()
-        .zip(operations.iter())
-        .map(|(operands, operation)| {
-            operands
-                .into_iter()
-                .reduce(|total, operand| match operation {
-                    Operation::Sum => total + operand,
-                    Operation::Multiplication => total * operand,
-                })
-                .unwrap()
-        })
-        .sum()
+    if let Some(orientation) = env::args().skip_while(|arg| arg != "--orientation").nth(1) {
+        let orientation = orientation.parse::<Orientation>().unwrap();
+        match day06(&contents, orientation) {
+            Ok(worksheet) => println!("Day06 {orientation:?} result: {}", worksheet.total),
+            Err(error) => println!("Day06 {orientation:?} error: {error}"),
+        }
+        return;
+    }
+    let part1 = day06_part1(&contents);
+    let part2 = day06_part2(&contents);
+    if env::args().any(|arg| arg == "--problems") {
+        for (part, worksheet) in [(1, &part1), (2, &part2)] {
+            if let Ok(worksheet) = worksheet {
+                println!("Day06 part {part} problems:");
+                for answer in &worksheet.answers {
+                    println!("{answer}");
+                }
+            }
+        }
+    }
+    if env::args().any(|arg| arg == "--compare")
+        && let (Ok(part1), Ok(part2)) = (&part1, &part2)
+        && part1.total != part2.total
+    {
+        println!("Day06 problems read differently:");
+        for (before, after) in changed_problems(part1, part2) {
+            println!(
+                "columns {}-{}: {} => {}",
+                after.problem.columns.start + 1,
+                after.problem.columns.end,
+                before,
+                after
+            );
+        }
+    }
+    match part1 {
+        Ok(worksheet) => println!("Day06 part 1 result: {}", worksheet.total),
+        Err(error) => println!("Day06 part 1 error: {error}"),
+    }
+    match part2 {
+        Ok(worksheet) => println!("Day06 part 2 result: {}", worksheet.total),
+        Err(error) => println!("Day06 part 2 error: {error}"),
+    }
+}
+
+fn day06_part1(input: &str) -> Result<Worksheet, WorksheetError> {
+    day06(input, Orientation::Rows)
+}
+
+fn day06_part2(input: &str) -> Result<Worksheet, WorksheetError> {
+    day06(input, Orientation::Columns)
+}
+
+fn day06(input: &str, orientation: Orientation) -> Result<Worksheet, WorksheetError> {
+    let problems = match orientation {
+        Orientation::Rows | Orientation::RowsRightToLeft => read_rows(input, orientation)?,
+        Orientation::Columns | Orientation::ColumnsBottomToTop => read_columns(input, orientation)?,
+    };
+    solve(problems)
+}
+
+// How the digits of every operand are laid out on the worksheet. Operands are taken from the top
+// row down, or from the rightmost column to the left as cephalopods read right to left, which
+// matters for `-`, `/`, `^` and `|`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Orientation {
+    // One number per row, read left to right
+    Rows,
+    // One number per row, with its digits read right to left
+    RowsRightToLeft,
+    // One number per column, read top to bottom
+    Columns,
+    // One number per column, read bottom to top
+    ColumnsBottomToTop,
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "rows" => Ok(Orientation::Rows),
+            "rows-rtl" => Ok(Orientation::RowsRightToLeft),
+            "columns" => Ok(Orientation::Columns),
+            "columns-btt" => Ok(Orientation::ColumnsBottomToTop),
+            _ => Err(format!("unknown orientation {input:?}")),
+        }
+    }
+}
+
+fn solve(problems: Vec<Problem>) -> Result<Worksheet, WorksheetError> {
+    let mut worksheet = Worksheet {
+        answers: Vec::new(),
+        total: 0,
+    };
+    for (i, problem) in problems.into_iter().enumerate() {
+        let error = |error| WorksheetError::Operation {
+            problem: i + 1,
+            error,
+        };
+        let result = problem
+            .operation
+            .evaluate(&problem.operands)
+            .map_err(error)?;
+        worksheet.total = worksheet
+            .total
+            .checked_add(result)
+            .ok_or(error(OperationError::Overflow))?;
+        worksheet.answers.push(Answer { problem, result });
+    }
+    Ok(worksheet)
+}
+
+// Pairs of answers for the same problem whose operands or result differ between two readings of
+// the worksheet
+fn changed_problems<'a>(
+    before: &'a Worksheet,
+    after: &'a Worksheet,
+) -> Vec<(&'a Answer, &'a Answer)> {
+    before
+        .answers
+        .iter()
+        .zip(&after.answers)
+        .filter(|(a, b)| a.problem.operands != b.problem.operands || a.result != b.result)
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Problem {
+    // Worksheet columns covered by the problem, counted from 0
+    columns: Range<usize>,
+    operands: Vec<i64>,
+    operation: Operation,
+}
+
+#[derive(Debug, PartialEq)]
+struct Answer {
+    problem: Problem,
+    result: i64,
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operands = self
+            .problem
+            .operands
+            .iter()
+            .map(|operand| operand.to_string())
+            .collect::<Vec<String>>();
+        let symbol = format!(" {} ", self.problem.operation.symbol());
+        write!(f, "{} = {}", operands.join(&symbol), self.result)
+    }
+}
+
+// Answers are kept in reading order
+#[derive(Debug, PartialEq)]
+struct Worksheet {
+    answers: Vec<Answer>,
+    total: i64,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum Operation {
     Sum,
     Multiplication,
+    Subtraction,
+    Division,
+    Min,
+    Max,
+    Power,
+    Concatenation,
 }
 
-fn read_input(input: &str) -> IResult<&str, (Vec<Vec<u64>>, Vec<Operation>)> {
-    separated_pair(
-        separated_list1(multispace1, separated_list1(space1, read_number)),
-        multispace1,
-        separated_list1(space1, read_operation),
-    )
-    .parse_complete(input)
+#[derive(Debug, PartialEq)]
+enum OperationError {
+    DivisionByZero,
+    Overflow,
 }
-fn read_input_part2(input: &str) -> IResult<&str, (Vec<Vec<u64>>, Vec<Operation>)> {
-    separated_pair(
-        map(
-            separated_list1(
-                line_ending,
-                many1(satisfy(|c| c.is_ascii_digit() || c == ' ')),
-            ),
-            read_cephalopod_numbers,
-        ),
-        line_ending,
-        separated_list1(space1, read_operation),
-    )
-    .parse_complete(input)
+
+impl Display for OperationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OperationError::DivisionByZero => write!(f, "division by zero"),
+            OperationError::Overflow => write!(f, "result out of range"),
+        }
+    }
+}
+
+// Problems are numbered from 1 in reading order
+#[derive(Debug, PartialEq)]
+enum WorksheetError {
+    MissingOperators,
+    MalformedProblem {
+        problem: usize,
+        reason: String,
+    },
+    Operation {
+        problem: usize,
+        error: OperationError,
+    },
 }
 
-fn read_cephalopod_numbers(lines: Vec<Vec<char>>) -> Vec<Vec<u64>> {
-    let mut numbers = Vec::new();
-    let mut current_numbers = Vec::new();
-    for column in 0..lines[0].len() {
-        let mut is_column_spaces = true;
-        let mut number = String::new();
-        for line in lines.iter() {
-            let new_char = line[column];
-            number.push(new_char);
-            is_column_spaces = is_column_spaces && new_char == ' ';
+impl Display for WorksheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorksheetError::MissingOperators => write!(f, "missing operator row"),
+            WorksheetError::MalformedProblem { problem, reason } => {
+                write!(f, "problem {problem} is malformed: {reason}")
+            }
+            WorksheetError::Operation { problem, error } => write!(f, "problem {problem}: {error}"),
         }
-        if is_column_spaces {
-            numbers.push(current_numbers);
-            current_numbers = Vec::new();
-        } else {
-            current_numbers.push(number.trim().parse().unwrap());
+    }
+}
+
+impl Operation {
+    fn symbol(&self) -> char {
+        match self {
+            Operation::Sum => '+',
+            Operation::Multiplication => '*',
+            Operation::Subtraction => '-',
+            Operation::Division => '/',
+            Operation::Min => '<',
+            Operation::Max => '>',
+            Operation::Power => '^',
+            Operation::Concatenation => '|',
+        }
+    }
+
+    fn apply(&self, a: i64, b: i64) -> Result<i64, OperationError> {
+        let result = match self {
+            Operation::Sum => a.checked_add(b),
+            Operation::Multiplication => a.checked_mul(b),
+            Operation::Subtraction => a.checked_sub(b),
+            Operation::Division if b == 0 => return Err(OperationError::DivisionByZero),
+            Operation::Division => a.checked_div(b),
+            Operation::Min => Some(a.min(b)),
+            Operation::Max => Some(a.max(b)),
+            Operation::Power => u32::try_from(b)
+                .ok()
+                .and_then(|exponent| a.checked_pow(exponent)),
+            Operation::Concatenation => {
+                let digits = b.checked_ilog10().unwrap_or(0) + 1;
+                10_i64
+                    .checked_pow(digits)
+                    .and_then(|shift| a.checked_mul(shift))
+                    .and_then(|shifted| shifted.checked_add(b))
+            }
+        };
+        result.ok_or(OperationError::Overflow)
+    }
+
+    // Operands are combined from left to right
+    fn evaluate(&self, operands: &[i64]) -> Result<i64, OperationError> {
+        operands[1..]
+            .iter()
+            .try_fold(operands[0], |total, &operand| self.apply(total, operand))
+    }
+}
+
+// Every row holds one number per problem, separated by spaces
+fn read_rows(input: &str, orientation: Orientation) -> Result<Vec<Problem>, WorksheetError> {
+    let rows = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(read_words)
+        .collect::<Vec<Vec<(Range<usize>, &str)>>>();
+    let Some((operator_row, number_rows)) = rows.split_last() else {
+        return Err(WorksheetError::MissingOperators);
+    };
+    if let Some(row) = number_rows
+        .iter()
+        .position(|row| row.len() > operator_row.len())
+    {
+        return Err(WorksheetError::MalformedProblem {
+            problem: operator_row.len() + 1,
+            reason: format!("row {} has a number without operator", row + 1),
+        });
+    }
+    operator_row
+        .iter()
+        .enumerate()
+        .map(|(i, (columns, symbol))| {
+            let malformed = |reason: String| WorksheetError::MalformedProblem {
+                problem: i + 1,
+                reason,
+            };
+            let operation = read_operation(symbol)
+                .ok()
+                .filter(|(rest, _)| rest.is_empty())
+                .map(|(_, operation)| operation)
+                .ok_or(malformed(format!("unknown operator {symbol:?}")))?;
+            let mut columns = columns.clone();
+            let mut operands = Vec::new();
+            for (row, words) in number_rows.iter().enumerate() {
+                let (word_columns, word) = words
+                    .get(i)
+                    .ok_or(malformed(format!("row {} has no number", row + 1)))?;
+                if !word.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(malformed(format!(
+                        "invalid number {word:?} in row {}",
+                        row + 1
+                    )));
+                }
+                let word = match orientation {
+                    Orientation::RowsRightToLeft => word.chars().rev().collect(),
+                    _ => word.to_string(),
+                };
+                operands.push(word.parse().map_err(|_| {
+                    malformed(format!("number {word} in row {} is too large", row + 1))
+                })?);
+                columns = columns.start.min(word_columns.start)..columns.end.max(word_columns.end);
+            }
+            if operands.is_empty() {
+                return Err(malformed("no numbers".to_string()));
+            }
+            Ok(Problem {
+                columns,
+                operands,
+                operation,
+            })
+        })
+        .collect()
+}
+
+// Words of a line along with the columns they cover
+fn read_words(line: &str) -> Vec<(Range<usize>, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (column, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(column),
+            (true, Some(word_start)) => {
+                words.push((word_start..column, &line[word_start..column]));
+                start = None;
+            }
+            _ => {}
         }
     }
-    numbers.push(current_numbers);
-    numbers
+    words
 }
 
-fn read_number(input: &str) -> IResult<&str, u64> {
-    map(digit1, |s: &str| s.parse().unwrap()).parse_complete(input)
+// Splits the worksheet into problems using the operator row: every operator starts a problem
+// that spans the columns up to the next operator. Lines are padded with spaces to the longest
+// one, as editors often trim trailing spaces.
+fn read_columns(input: &str, orientation: Orientation) -> Result<Vec<Problem>, WorksheetError> {
+    let lines = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().collect::<Vec<char>>())
+        .collect::<Vec<Vec<char>>>();
+    let Some((operator_row, digit_rows)) = lines.split_last() else {
+        return Err(WorksheetError::MissingOperators);
+    };
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let starts = operator_row
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(column, _)| column)
+        .collect::<Vec<usize>>();
+    if starts.is_empty() {
+        return Err(WorksheetError::MissingOperators);
+    }
+    let read_column = |column: usize| {
+        let digits = digit_rows
+            .iter()
+            .map(|row| row.get(column).copied().unwrap_or(' '));
+        match orientation {
+            Orientation::ColumnsBottomToTop => digits.rev().collect::<String>(),
+            _ => digits.collect::<String>(),
+        }
+    };
+    if let Some(column) = (0..starts[0]).find(|&column| !read_column(column).trim().is_empty()) {
+        return Err(WorksheetError::MalformedProblem {
+            problem: 1,
+            reason: format!("column {} is left of its operator", column + 1),
+        });
+    }
+
+    let mut problems = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let malformed = |reason: String| WorksheetError::MalformedProblem {
+            problem: i + 1,
+            reason,
+        };
+        let symbol = operator_row[start];
+        let (_, operation) = read_operation(&symbol.to_string())
+            .map_err(|_| malformed(format!("unknown operator {symbol:?}")))?;
+        let end = starts.get(i + 1).copied().unwrap_or(width);
+        let mut operands = Vec::new();
+        let mut last_column = start;
+        let mut separated = false;
+        for column in start..end {
+            let digits = read_column(column);
+            let digits = digits.trim();
+            if digits.is_empty() {
+                separated = true;
+                continue;
+            }
+            if separated {
+                return Err(malformed(format!("column {} has no operator", column + 1)));
+            }
+            if !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(malformed(format!(
+                    "invalid number {digits:?} in column {}",
+                    column + 1
+                )));
+            }
+            operands.push(digits.parse().map_err(|_| {
+                malformed(format!(
+                    "number {digits} in column {} is too large",
+                    column + 1
+                ))
+            })?);
+            last_column = column;
+        }
+        if operands.is_empty() {
+            return Err(malformed("no numbers".to_string()));
+        }
+        operands.reverse();
+        problems.push(Problem {
+            columns: start..last_column + 1,
+            operands,
+            operation,
+        });
+    }
+    Ok(problems)
 }
 
 fn read_operation(input: &str) -> IResult<&str, Operation> {
     alt((
         map(tag("+"), |_| Operation::Sum),
         map(tag("*"), |_| Operation::Multiplication),
+        map(tag("-"), |_| Operation::Subtraction),
+        map(tag("/"), |_| Operation::Division),
+        map(tag("<"), |_| Operation::Min),
+        map(tag(">"), |_| Operation::Max),
+        map(tag("^"), |_| Operation::Power),
+        map(tag("|"), |_| Operation::Concatenation),
     ))
     .parse_complete(input)
 }
@@ -123,31 +457,152 @@ fn read_operation(input: &str) -> IResult<&str, Operation> {
 mod tests {
     use super::*;
 
+    fn total(worksheet: Result<Worksheet, WorksheetError>) -> Result<i64, WorksheetError> {
+        worksheet.map(|worksheet| worksheet.total)
+    }
+
     #[test]
     fn part1_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day06_part1(&contents);
+        let result = day06_part1(&contents).unwrap().total;
         assert_eq!(result, 4277556);
     }
 
     #[test]
     fn part1_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day06_part1(&contents);
+        let result = day06_part1(&contents).unwrap().total;
         assert_eq!(result, 5346286649122);
     }
 
     #[test]
     fn part2_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day06_part2(&contents);
+        let result = day06_part2(&contents).unwrap().total;
         assert_eq!(result, 3263827);
     }
 
     #[test]
     fn part2_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day06_part2(&contents);
+        let result = day06_part2(&contents).unwrap().total;
         assert_eq!(result, 10389131401929);
     }
+
+    #[test]
+    fn operations_apply_left_to_right() {
+        let evaluate = |operation: Operation, operands: &[i64]| operation.evaluate(operands);
+        assert_eq!(evaluate(Operation::Subtraction, &[10, 3, 4]), Ok(3));
+        assert_eq!(evaluate(Operation::Division, &[100, 7, 2]), Ok(7));
+        assert_eq!(evaluate(Operation::Min, &[5, 3, 8]), Ok(3));
+        assert_eq!(evaluate(Operation::Max, &[5, 3, 8]), Ok(8));
+        assert_eq!(evaluate(Operation::Power, &[2, 3, 2]), Ok(64));
+        assert_eq!(
+            evaluate(Operation::Concatenation, &[12, 0, 345]),
+            Ok(120345)
+        );
+        assert_eq!(
+            evaluate(Operation::Division, &[1, 0]),
+            Err(OperationError::DivisionByZero)
+        );
+        assert_eq!(
+            evaluate(Operation::Power, &[10, 19]),
+            Err(OperationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn both_parts_read_every_operator() {
+        let contents = "8 9 2 3 12 4 \n2 3 5 3 1  2 \n-  / < > ^  | ";
+        assert_eq!(total(day06_part1(contents)), Ok(6 + 3 + 2 + 3 + 12 + 42));
+        let contents = "84 95 23 31 20 4\n26 37 50 32 13 2\n-  /  <  >  ^  |";
+        // Columns are read right to left, so 57 / 93 rounds down to 0
+        assert_eq!(
+            total(day06_part2(contents)),
+            Ok((46 - 82) + 25 + 33 + 3_i64.pow(21) + 42)
+        );
+        assert_eq!(
+            total(day06_part1("4 6\n0 2\n/ +")),
+            Err(WorksheetError::Operation {
+                problem: 1,
+                error: OperationError::DivisionByZero
+            })
+        );
+    }
+
+    #[test]
+    fn part2_pads_trimmed_lines() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let trimmed = contents
+            .lines()
+            .map(|line| line.trim_end())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        assert_eq!(total(day06_part2(&trimmed)), Ok(3263827));
+        assert_eq!(total(day06_part2("1\n23 4\n+  *")), Ok(15 + 4));
+    }
+
+    #[test]
+    fn part2_reports_malformed_problem() {
+        let malformed = |contents| match day06_part2(contents) {
+            Err(WorksheetError::MalformedProblem { problem, .. }) => Some(problem),
+            _ => None,
+        };
+        assert_eq!(malformed("12 34 5\n+  ?  *"), Some(2));
+        assert_eq!(malformed("12 34 5\n+  *"), Some(2));
+        assert_eq!(malformed("12 3x 5\n+  *  *"), Some(2));
+        assert_eq!(malformed("12 34\n+  *   *"), Some(3));
+        assert_eq!(malformed(" 12 3\n  + *"), Some(1));
+        assert_eq!(
+            total(day06_part2("\n")),
+            Err(WorksheetError::MissingOperators)
+        );
+    }
+
+    #[test]
+    fn answers_keep_reading_order() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let worksheet = day06_part2(&contents).unwrap();
+        let answers = worksheet
+            .answers
+            .iter()
+            .map(|answer| answer.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            answers,
+            vec![
+                "356 * 24 * 1 = 8544",
+                "8 + 248 + 369 = 625",
+                "175 * 581 * 32 = 3253600",
+                "4 + 431 + 623 = 1058"
+            ]
+        );
+        assert_eq!(worksheet.answers[1].problem.columns, 4..7);
+    }
+
+    #[test]
+    fn changed_problems_between_readings() {
+        let contents = "12 3\n34 5\n+  *";
+        let part1 = day06_part1(contents).unwrap();
+        let part2 = day06_part2(contents).unwrap();
+        assert_eq!(part1.answers[0].to_string(), "12 + 34 = 46");
+        assert_eq!(part1.answers[0].problem.columns, 0..2);
+        let changed = changed_problems(&part1, &part2);
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0].1.to_string(), "24 + 13 = 37");
+        assert_eq!(changed[0].1.problem.columns, 0..2);
+        assert_eq!(changed[1].0.to_string(), "3 * 5 = 15");
+        assert_eq!(changed[1].1.to_string(), "35 = 35");
+    }
+
+    #[test]
+    fn every_orientation_reads_the_same_worksheet() {
+        let contents = "12 3\n34 5\n-  *";
+        let result = |orientation| total(day06(contents, orientation));
+        assert_eq!(result(Orientation::Rows), Ok(12 - 34 + 3 * 5));
+        assert_eq!(result(Orientation::RowsRightToLeft), Ok(21 - 43 + 3 * 5));
+        assert_eq!(result(Orientation::Columns), Ok(24 - 13 + 35));
+        assert_eq!(result(Orientation::ColumnsBottomToTop), Ok(42 - 31 + 53));
+        assert_eq!("columns-btt".parse(), Ok(Orientation::ColumnsBottomToTop));
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
-    collections::HashMap,
+    env,
+    fmt::{self, Display},
     fs,
     ops::{Index, IndexMut},
 };
@@ -15,111 +16,286 @@ use nom::{
 
 fn main() {
     let contents = fs::read_to_string("input").unwrap();
-    let result = day07_part1(&contents);
-    println!("Day07 part 1 result: {result}");
-    let result = day07_part2(&contents);
-    println!("Day07 part 2 result: {result}");
+    if env::args().any(|arg| arg == "--trace") {
+        let trace = day07_trace(&contents);
+        println!(
+            "Day07 trace: {} energised cells, {} splits",
+            trace.energised, trace.splits
+        );
+        return;
+    }
+    match day07_part1(&contents) {
+        Ok(result) => println!("Day07 part 1 result: {result}"),
+        Err(error) => println!("Day07 part 1 error: {error}"),
+    }
+    match day07_part2(&contents) {
+        Ok(result) => println!("Day07 part 2 result: {result}"),
+        Err(error) => println!("Day07 part 2 error: {error}"),
+    }
+    if env::args().any(|arg| arg == "--exits") {
+        let (_, matrix) = read_input(&contents).unwrap();
+        if let Ok(sweep) = sweep(&matrix) {
+            println!("Day07 bottom exits:");
+            for (column, (timelines, chance)) in
+                sweep.exits.iter().zip(&sweep.distribution).enumerate()
+            {
+                if *timelines > 0 {
+                    println!("column {column}: {timelines} timelines, {chance:.3e}");
+                }
+            }
+        }
+    }
 }
 
-fn day07_part1(input: &str) -> usize {
-    let (_, mut matrix) = read_input(input).unwrap();
-    let start_pos = matrix
+fn day07_part1(input: &str) -> Result<usize, SweepError> {
+    let (_, matrix) = read_input(input).unwrap();
+    Ok(sweep(&matrix)?.splits)
+}
+
+fn day07_part2(input: &str) -> Result<usize, SweepError> {
+    let (_, matrix) = read_input(input).unwrap();
+    Ok(sweep(&matrix)?.timelines)
+}
+
+// The row sweep only follows beams moving down. Mirrors, `-` splitters and splitters next to
+// each other send beams sideways and need a trace.
+#[derive(Debug, PartialEq)]
+struct SweepError {
+    pos: Position,
+}
+
+impl Display for SweepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "beam can't be swept at row {} column {}, use --trace",
+            self.pos.0 + 1,
+            self.pos.1 + 1
+        )
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Sweep {
+    // Splitters reached by at least one beam
+    splits: usize,
+    // Paths a particle can take from any source, ending either at the bottom or past a side
+    timelines: usize,
+    // Timelines leaving through the bottom of each column
+    exits: Vec<usize>,
+    // Chance of a particle leaving through the bottom of each column, when it comes from any
+    // source with the same chance and goes either way at every split. The chance of leaving past
+    // a side or hitting an absorber is dropped, so it only adds up to 1 when neither happens.
+    distribution: Vec<f64>,
+}
+
+// Moves every beam down one row at a time, keeping how many timelines reach each column and the
+// chance of a particle being there. Every `S` starts a new beam below it.
+fn sweep(matrix: &Matrix<Cell>) -> Result<Sweep, SweepError> {
+    let sources = matrix
         .iter()
-        .find(|&pos| matrix[pos] == Cell::Start)
-        .unwrap();
-    let next = matrix
-        .get_next_position(start_pos, Direction::Down)
-        .unwrap();
-    throw_beam(&mut matrix, next)
-}
-
-fn throw_beam(matrix: &mut Matrix<Cell>, pos: Position) -> usize {
-    match matrix[pos] {
-        Cell::Empty => {
-            matrix[pos] = Cell::Beam;
-            matrix
-                .get_next_position(pos, Direction::Down)
-                .map(|next| throw_beam(matrix, next))
-                .unwrap_or(0)
+        .filter(|&pos| matrix[pos] == Cell::Start)
+        .count();
+    let mut sweep = Sweep::default();
+    let (mut beams, mut next_beams) = (vec![0; matrix.n()], vec![0; matrix.n()]);
+    let (mut chances, mut next_chances) = (vec![0.0; matrix.n()], vec![0.0; matrix.n()]);
+    for i in 0..matrix.m() {
+        next_beams.fill(0);
+        next_chances.fill(0.0);
+        for j in 0..matrix.n() {
+            let (count, chance) = (beams[j], chances[j]);
+            match matrix[(i, j)] {
+                Cell::Start => {
+                    next_beams[j] += count + 1;
+                    next_chances[j] += chance + 1.0 / sources as f64;
+                }
+                _ if count == 0 => {}
+                Cell::Splitter => {
+                    sweep.splits += 1;
+                    for direction in [Direction::Left, Direction::Right] {
+                        let Some(beside) = matrix.get_next_position((i, j), direction) else {
+                            sweep.timelines += count;
+                            continue;
+                        };
+                        // The beam enters the cell beside the splitter before going down
+                        match matrix[beside] {
+                            Cell::Empty | Cell::Start | Cell::VerticalSplitter => {
+                                next_beams[beside.1] += count;
+                                next_chances[beside.1] += chance / 2.0;
+                            }
+                            Cell::Absorber => {}
+                            Cell::Splitter
+                            | Cell::SlashMirror
+                            | Cell::BackslashMirror
+                            | Cell::HorizontalSplitter => {
+                                return Err(SweepError { pos: beside });
+                            }
+                        }
+                    }
+                }
+                Cell::Empty | Cell::VerticalSplitter => {
+                    next_beams[j] += count;
+                    next_chances[j] += chance;
+                }
+                Cell::Absorber => {}
+                Cell::SlashMirror | Cell::BackslashMirror | Cell::HorizontalSplitter => {
+                    return Err(SweepError { pos: (i, j) });
+                }
+            }
         }
-        Cell::Splitter => {
-            let right_count = matrix
-                .get_next_position(pos, Direction::Right)
-                .map(|next| throw_beam(matrix, next))
-                .unwrap_or(0);
-            let left_count = matrix
-                .get_next_position(pos, Direction::Left)
-                .map(|next| throw_beam(matrix, next))
-                .unwrap_or(0);
-            right_count + left_count + 1
-        }
-        Cell::Beam => 0,
-        Cell::Start => unreachable!(),
+        (beams, next_beams) = (next_beams, beams);
+        (chances, next_chances) = (next_chances, chances);
     }
+    sweep.timelines += beams.iter().sum::<usize>();
+    sweep.exits = beams;
+    sweep.distribution = chances;
+    Ok(sweep)
 }
 
-fn day07_part2(input: &str) -> usize {
-    let (_, mut matrix) = read_input(input).unwrap();
-    let start_pos = matrix
+fn day07_trace(input: &str) -> Trace {
+    let (_, matrix) = read_input(input).unwrap();
+    let sources = matrix
         .iter()
-        .find(|&pos| matrix[pos] == Cell::Start)
-        .unwrap();
-    let next = matrix
-        .get_next_position(start_pos, Direction::Down)
-        .unwrap();
-    throw_quantum_beam(&mut matrix, next, &mut HashMap::new()) + 1
+        .filter(|&pos| matrix[pos] == Cell::Start)
+        .map(|pos| (pos, Direction::Down))
+        .collect::<Vec<_>>();
+    trace(&matrix, sources)
 }
 
-fn throw_quantum_beam(
-    matrix: &mut Matrix<Cell>,
-    pos: Position,
-    cache: &mut HashMap<Position, usize>,
-) -> usize {
-    if cache.contains_key(&pos) {
-        return cache[&pos];
-    }
-    let value = match matrix[pos] {
-        Cell::Empty | Cell::Beam => {
-            matrix[pos] = Cell::Beam;
-            matrix
-                .get_next_position(pos, Direction::Down)
-                .map(|next| throw_quantum_beam(matrix, next, cache))
-                .unwrap_or(0)
+#[derive(Debug, PartialEq)]
+struct Trace {
+    // Cells crossed by at least one beam
+    energised: usize,
+    // Splitters that split at least one beam
+    splits: usize,
+}
+
+// Follows beams in every direction starting with the given ones entering their cells. Each cell
+// remembers the directions beams entered it with, so beams that merge or go around a loop stop
+// there.
+fn trace(matrix: &Matrix<Cell>, sources: Vec<(Position, Direction)>) -> Trace {
+    let mut visited = Matrix {
+        inner: vec![vec![0_u8; matrix.n()]; matrix.m()],
+    };
+    let mut beams = sources;
+    while let Some((pos, direction)) = beams.pop() {
+        if visited[pos] & direction.bit() != 0 {
+            continue;
         }
-        Cell::Splitter => {
-            let right_count = matrix
-                .get_next_position(pos, Direction::Right)
-                .map(|next| throw_quantum_beam(matrix, next, cache))
-                .unwrap_or(0);
-            let left_count = matrix
-                .get_next_position(pos, Direction::Left)
-                .map(|next| throw_quantum_beam(matrix, next, cache))
-                .unwrap_or(0);
-            right_count + left_count + 1
+        visited[pos] |= direction.bit();
+        let mut push = |pos: Position, direction: Direction| {
+            if let Some(next) = matrix.get_next_position(pos, direction) {
+                beams.push((next, direction));
+            }
+        };
+        match (matrix[pos], direction.is_vertical()) {
+            (Cell::Empty | Cell::Start, _)
+            | (Cell::VerticalSplitter, true)
+            | (Cell::HorizontalSplitter, false) => push(pos, direction),
+            (Cell::Absorber, _) => {}
+            (Cell::SlashMirror, _) => push(pos, direction.reflect_slash()),
+            (Cell::BackslashMirror, _) => push(pos, direction.reflect_backslash()),
+            (Cell::VerticalSplitter | Cell::HorizontalSplitter, _) => {
+                for side in direction.sides() {
+                    push(pos, side);
+                }
+            }
+            // The beam keeps its direction and enters both cells beside the splitter
+            (Cell::Splitter, _) => {
+                for side in direction.sides() {
+                    if let Some(beside) = matrix.get_next_position(pos, side) {
+                        beams.push((beside, direction));
+                    }
+                }
+            }
         }
-        Cell::Start => unreachable!(),
-    };
-    cache.insert(pos, value);
-    value
+    }
+    let vertical = Direction::Up.bit() | Direction::Down.bit();
+    matrix.iter().fold(
+        Trace {
+            energised: 0,
+            splits: 0,
+        },
+        |mut trace, pos| {
+            let directions = visited[pos];
+            if directions != 0 {
+                trace.energised += 1;
+            }
+            let split = match matrix[pos] {
+                Cell::Splitter => directions != 0,
+                Cell::VerticalSplitter => directions & !vertical != 0,
+                Cell::HorizontalSplitter => directions & vertical != 0,
+                _ => false,
+            };
+            if split {
+                trace.splits += 1;
+            }
+            trace
+        },
+    )
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Cell {
     Start,
     Empty,
+    // Beams moving down continue down from both cells beside it
     Splitter,
-    Beam,
+    SlashMirror,
+    BackslashMirror,
+    Absorber,
+    // Splits beams moving left or right into beams moving up and down
+    VerticalSplitter,
+    // Splits beams moving up or down into beams moving left and right
+    HorizontalSplitter,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Direction {
-    #[allow(unused)]
     Up,
     Right,
     Down,
     Left,
 }
 
+impl Direction {
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+
+    fn is_vertical(&self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+
+    fn sides(&self) -> [Direction; 2] {
+        if self.is_vertical() {
+            [Direction::Left, Direction::Right]
+        } else {
+            [Direction::Up, Direction::Down]
+        }
+    }
+
+    // Direction after hitting a `/` mirror
+    fn reflect_slash(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    // Direction after hitting a `\` mirror
+    fn reflect_backslash(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Down,
+        }
+    }
+}
+
 type Position = (usize, usize);
 
 #[derive(Debug)]
@@ -183,6 +359,11 @@ fn read_input(input: &str) -> IResult<&str, Matrix<Cell>> {
                 map(tag("S"), |_| Cell::Start),
                 map(tag("^"), |_| Cell::Splitter),
                 map(tag("."), |_| Cell::Empty),
+                map(tag("/"), |_| Cell::SlashMirror),
+                map(tag("\\"), |_| Cell::BackslashMirror),
+                map(tag("#"), |_| Cell::Absorber),
+                map(tag("|"), |_| Cell::VerticalSplitter),
+                map(tag("-"), |_| Cell::HorizontalSplitter),
             ))),
         ),
         |inner| Matrix { inner },
@@ -197,28 +378,157 @@ mod tests {
     #[test]
     fn part1_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day07_part1(&contents);
+        let result = day07_part1(&contents).unwrap();
         assert_eq!(result, 21);
     }
 
     #[test]
     fn part1_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day07_part1(&contents);
+        let result = day07_part1(&contents).unwrap();
         assert_eq!(result, 1609);
     }
 
     #[test]
     fn part2_correct_output_for_test_input() {
         let contents = fs::read_to_string("test_input").unwrap();
-        let result = day07_part2(&contents);
+        let result = day07_part2(&contents).unwrap();
         assert_eq!(result, 40);
     }
 
     #[test]
     fn part2_correct_output_for_input() {
         let contents = fs::read_to_string("input").unwrap();
-        let result = day07_part2(&contents);
+        let result = day07_part2(&contents).unwrap();
         assert_eq!(result, 12472142047197);
     }
+
+    #[test]
+    fn sweeps_tall_manifolds() {
+        let mut contents = String::from(".S.\n");
+        contents.push_str(&"...\n".repeat(100_000));
+        contents.push_str(".^.\n...\n");
+        assert_eq!(day07_part1(&contents), Ok(1));
+        assert_eq!(day07_part2(&contents), Ok(2));
+    }
+
+    #[test]
+    fn timelines_leaving_through_a_side_are_counted() {
+        let contents = "S..\n^..\n...";
+        assert_eq!(day07_part1(contents), Ok(1));
+        assert_eq!(day07_part2(contents), Ok(2));
+    }
+
+    #[test]
+    fn trace_matches_part1_splits() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        assert_eq!(day07_trace(&contents).splits, 21);
+    }
+
+    #[test]
+    fn trace_stops_looping_beams() {
+        let contents = ".S...\n.-..\\\n.....\n.\\../";
+        assert_eq!(
+            day07_trace(contents),
+            Trace {
+                energised: 12,
+                splits: 1
+            }
+        );
+    }
+
+    #[test]
+    fn trace_through_absorbers_and_splitters() {
+        assert_eq!(
+            day07_trace("S..\n|..\n#.."),
+            Trace {
+                energised: 3,
+                splits: 0
+            }
+        );
+        assert_eq!(
+            day07_trace(".S.\n|-|\n..."),
+            Trace {
+                energised: 8,
+                splits: 3
+            }
+        );
+    }
+
+    #[test]
+    fn exits_histogram_and_distribution() {
+        let contents = fs::read_to_string("test_input").unwrap();
+        let (_, matrix) = read_input(&contents).unwrap();
+        let sweep = sweep(&matrix).unwrap();
+        assert_eq!(sweep.exits.iter().sum::<usize>(), 40);
+        assert_eq!(
+            sweep.exits,
+            vec![1, 0, 2, 0, 10, 0, 11, 0, 11, 0, 2, 1, 1, 0, 1]
+        );
+        assert!((sweep.distribution.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn several_sources() {
+        let (_, matrix) = read_input(".S.S.\n.....\n...^.\n.....").unwrap();
+        let sweep = sweep(&matrix).unwrap();
+        assert_eq!(sweep.splits, 1);
+        assert_eq!(sweep.timelines, 3);
+        assert_eq!(sweep.exits, vec![0, 1, 1, 0, 1]);
+        assert_eq!(sweep.distribution, vec![0.0, 0.5, 0.25, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn parts_reject_sideways_beams() {
+        let contents = ".S.\n./-\n...";
+        let error = Err(SweepError { pos: (1, 1) });
+        assert_eq!(day07_part1(contents), error);
+        assert_eq!(day07_part2(contents), error);
+        assert_eq!(day07_part1(".S.\n.|.\n.#."), Ok(0));
+        assert_eq!(day07_part2(".S.\n.|.\n.#."), Ok(0));
+    }
+
+    #[test]
+    fn distribution_drops_side_exits() {
+        let (_, matrix) = read_input("S..\n^..\n...").unwrap();
+        let sweep = sweep(&matrix).unwrap();
+        assert_eq!(sweep.timelines, 2);
+        assert_eq!(sweep.exits, vec![0, 1, 0]);
+        assert_eq!(sweep.distribution, vec![0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn trace_follows_every_source() {
+        let contents = ".S.S.\n.....\n...^.\n.....";
+        let (_, matrix) = read_input(contents).unwrap();
+        assert_eq!(day07_trace(contents).splits, sweep(&matrix).unwrap().splits);
+        assert_eq!(
+            day07_trace(contents),
+            Trace {
+                energised: 11,
+                splits: 1
+            }
+        );
+    }
+
+    #[test]
+    fn sweep_checks_cells_beside_splitters() {
+        let contents = ".S.\n.^#\n...";
+        let (_, matrix) = read_input(contents).unwrap();
+        let sweep = sweep(&matrix).unwrap();
+        assert_eq!(sweep.exits, vec![1, 0, 0]);
+        assert_eq!(sweep.timelines, 1);
+        assert_eq!(sweep.distribution, vec![0.5, 0.0, 0.0]);
+        assert_eq!(sweep.splits, day07_trace(contents).splits);
+        let contents = ".S.\n.^/\n...";
+        assert_eq!(day07_part1(contents), Err(SweepError { pos: (1, 2) }));
+        assert_eq!(day07_part2(contents), Err(SweepError { pos: (1, 2) }));
+        assert_eq!(
+            day07_trace(contents),
+            Trace {
+                energised: 7,
+                splits: 1
+            }
+        );
+    }
 }